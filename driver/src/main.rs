@@ -1,194 +1,393 @@
-use std::{error::Error, time::Duration};
+use std::{
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use rusb::{
-    open_device_with_vid_pid, set_log_level, DeviceHandle, EndpointDescriptor, GlobalContext,
+use clap::{Parser, Subcommand};
+use flir::{
+    Calibration, ColorMode, DeviceInfo, FlirError, FlirOne, FlirOneBuilder, Frame, FrameStats,
+    Palette, RetryPolicy, TempUnit, ThermalImage, FLIR_ONE_PRODUCT_IDS, FLIR_VENDOR_ID,
 };
+use rusb::{open_device_with_vid_pid, DeviceHandle, GlobalContext};
+use serde::Serialize;
 
-#[derive(Debug)]
-pub enum ProtocolType {
-    CONFIG,
-    FILEIO,
-    FRAME,
-}
-
-#[derive(Debug)]
-pub struct FlirOne<'a> {
-    handle: DeviceHandle<GlobalContext>,
-    config: (EndpointDescriptor<'a>, EndpointDescriptor<'a>),
-    frame: (EndpointDescriptor<'a>, EndpointDescriptor<'a>),
-    fileio: (EndpointDescriptor<'a>, EndpointDescriptor<'a>),
-    connected: bool,
-    expect_file_data: bool,
-    expect_frame_data: bool,
-}
-
-impl<'a> FlirOne<'a> {
-    pub fn toggle_communication(
-        &mut self,
-        protocol_type: ProtocolType,
-        start: bool,
-    ) -> Result<(), Box<dyn Error>> {
-        let control_cmd = if start { 1 } else { 0 };
-        let index = match protocol_type {
-            ProtocolType::CONFIG => 0,
-            ProtocolType::FILEIO => {
-                self.expect_file_data = true;
-                1
-            }
-            ProtocolType::FRAME => {
-                self.expect_frame_data = true;
-                2
-            }
-        };
+/// Captures thermal images from a FLIR One and saves them as PNGs, with the raw
+/// radiometric data alongside as 16-bit TIFFs.
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Frames to capture.
+    #[arg(long, default_value_t = 10)]
+    frames: usize,
+    /// Directory the images are saved to.
+    #[arg(long, default_value = ".")]
+    out: PathBuf,
+    /// Instead of saving images, clear the terminal and show live min/max/center
+    /// temperatures.
+    #[arg(long)]
+    watch: bool,
+    /// Use typical FLIR One constants instead of this unit's calibration, for cameras
+    /// without a stored image to read it from. Temperatures can be off by several degrees.
+    #[arg(long, global = true)]
+    default_calibration: bool,
+    /// Unit temperatures are printed in: celsius, fahrenheit or kelvin.
+    #[arg(long, global = true, default_value = "celsius")]
+    unit: TempUnit,
+}
 
-        let res = self.handle.write_control(
-            0x1,
-            11,
-            control_cmd,
-            index,
-            &Vec::new(),
-            Duration::from_secs(1),
-        )?;
-        println!("res {res}");
-        Ok(())
+#[derive(Subcommand)]
+enum Command {
+    /// Save frames as colorized PNGs and raw 16-bit TIFFs.
+    Capture {
+        /// Directory the images are saved to.
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+        #[arg(long, default_value_t = 1)]
+        frames: usize,
+        /// grayscale, ironbow, rainbow or arctic.
+        #[arg(long, default_value = "ironbow")]
+        palette: Palette,
+        /// Instead of the palette, show grayscale with pixels hotter than this many degrees
+        /// Celsius painted red.
+        #[arg(long)]
+        alarm_above: Option<f32>,
+        /// Like --alarm-above, for pixels colder than this. Given both, the alarm is the
+        /// range between them.
+        #[arg(long)]
+        alarm_below: Option<f32>,
+    },
+    /// Print the camera's USB descriptor tree.
+    Descriptors,
+    /// Print the camera's model, serial number, versions and hardware revision.
+    Info {
+        /// Also print the battery charge and whether it is charging.
+        #[arg(long)]
+        battery: bool,
+    },
+    /// Record frames to a directory, with a manifest.json describing the session.
+    Record {
+        dir: PathBuf,
+        #[arg(long, default_value_t = 100)]
+        frames: usize,
+    },
+    /// Capture one frame and print the temperature at a thermal pixel.
+    Probe {
+        #[arg(long)]
+        x: f32,
+        #[arg(long)]
+        y: f32,
+    },
+}
+
+/// How long to wait for the camera to enumerate, e.g. while it switches from charging
+/// to camera mode after being plugged in.
+const OPEN_TIMEOUT: Duration = Duration::from_secs(10);
+const OPEN_POLL: Duration = Duration::from_millis(250);
+const ALARM_COLOR: [u8; 3] = [255, 0, 0];
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let flir = open()?;
+    match cli.command {
+        Some(Command::Capture {
+            out,
+            frames,
+            palette,
+            alarm_above,
+            alarm_below,
+        }) => {
+            let mode = if alarm_above.is_some() || alarm_below.is_some() {
+                ColorMode::Isotherm {
+                    below: alarm_below,
+                    above: alarm_above,
+                    color: ALARM_COLOR,
+                }
+            } else {
+                palette.into()
+            };
+            capture(flir, &out, frames, mode, cli.default_calibration)?
+        }
+        Some(Command::Descriptors) => print!("{}", FlirOne::describe(&flir)?),
+        Some(Command::Info { battery }) => info(flir, battery)?,
+        Some(Command::Record { dir, frames }) => {
+            record(flir, &dir, frames, cli.default_calibration)?
+        }
+        Some(Command::Probe { x, y }) => probe(flir, x, y, cli.default_calibration, cli.unit)?,
+        None if cli.watch => watch(flir, cli.default_calibration, cli.unit)?,
+        None => capture(
+            flir,
+            &cli.out,
+            cli.frames,
+            Palette::Grayscale.into(),
+            cli.default_calibration,
+        )?,
     }
+    Ok(())
+}
 
-    pub fn connect(&mut self) -> Result<(), Box<dyn Error>> {
-        if !self.connected {
-            self.connected = true;
-            self.toggle_communication(ProtocolType::FILEIO, true)?;
+fn open() -> Result<DeviceHandle<GlobalContext>, Box<dyn Error>> {
+    let deadline = Instant::now() + OPEN_TIMEOUT;
+    loop {
+        if let Some(handle) = open_device_with_vid_pid(FLIR_VENDOR_ID, FLIR_ONE_PRODUCT_IDS[0]) {
+            return Ok(handle);
         }
-        Ok(())
+        if Instant::now() >= deadline {
+            return Err(FlirError::DeviceNotFound.into());
+        }
+        thread::sleep(OPEN_POLL);
     }
 }
 
-pub struct FlirOneBuilder<'a> {
-    config_read: Option<EndpointDescriptor<'a>>,
-    config_write: Option<EndpointDescriptor<'a>>,
-    frame_read: Option<EndpointDescriptor<'a>>,
-    frame_write: Option<EndpointDescriptor<'a>>,
-    fileio_read: Option<EndpointDescriptor<'a>>,
-    fileio_write: Option<EndpointDescriptor<'a>>,
+/// Claims the camera and finds its endpoints. The camera is released when the returned
+/// [`FlirOne`] is dropped.
+fn claim(flir: DeviceHandle<GlobalContext>) -> Result<FlirOne, Box<dyn Error>> {
+    let mut builder =
+        FlirOneBuilder::new(flir).retry_policy(RetryPolicy::new(3, Duration::from_millis(100)));
+    builder.auto_detect()?;
+    Ok(builder.build()?)
+}
 
-    handle: DeviceHandle<GlobalContext>,
+/// Claims the camera and waits for its first usable frame. The camera stops streaming
+/// when the returned [`FlirOne`] is dropped.
+fn start(flir: DeviceHandle<GlobalContext>) -> Result<(FlirOne, Frame), Box<dyn Error>> {
+    let mut flir = claim(flir)?;
+    let frame = flir.connect_and_wait()?;
+    Ok((flir, frame))
 }
 
-impl<'a> FlirOneBuilder<'a> {
-    pub fn new(handle: DeviceHandle<GlobalContext>) -> Self {
-        FlirOneBuilder {
-            config_read: None,
-            config_write: None,
-            frame_read: None,
-            frame_write: None,
-            fileio_read: None,
-            fileio_write: None,
-            handle,
-        }
+fn info(flir: DeviceHandle<GlobalContext>, battery: bool) -> Result<(), Box<dyn Error>> {
+    let mut flir = claim(flir)?;
+    flir.connect()?;
+    let info = flir.device_info()?;
+    let unknown = |value: &Option<String>| value.clone().unwrap_or_else(|| "unknown".into());
+    println!("product  {}", unknown(&info.strings.product));
+    println!("serial   {}", unknown(&info.strings.serial_number));
+    println!("firmware {}", unknown(&info.firmware_version));
+    println!("software {}", unknown(&info.software_version));
+    println!("hardware {}", unknown(&info.hardware_revision));
+    if battery {
+        let status = flir.battery_status()?;
+        let charging = if status.charging { " (charging)" } else { "" };
+        println!("battery  {}%{charging}", status.percentage);
     }
+    Ok(())
+}
 
-    pub fn config_read(mut self, endpoint: EndpointDescriptor<'a>) -> Self {
-        self.config_read = Some(endpoint);
-        self
+/// Saves `count` frames as `thermal_0000.png` and `thermal_0000.tiff`, ... in `dir`.
+/// Isotherms are found with the unit's calibration, see [`calibration`].
+fn capture(
+    flir: DeviceHandle<GlobalContext>,
+    dir: &Path,
+    count: usize,
+    mode: ColorMode,
+    default_calibration: bool,
+) -> Result<(), Box<dyn Error>> {
+    mode.check()?;
+    let (mut flir, _) = start(flir)?;
+    let calibration = match mode {
+        ColorMode::Isotherm { .. } => Some(calibration(&mut flir, default_calibration)?),
+        ColorMode::Palette(_) => None,
+    };
+    fs::create_dir_all(dir)?;
+    let frames = flir.frames().discard_shutter_frames().take_count(count);
+    for (index, frame) in frames.enumerate() {
+        let frame = frame?;
+        let png = dir.join(format!("thermal_{index:04}.png"));
+        frame.save_png(&png, mode, calibration.as_ref())?;
+        let tiff = png.with_extension("tiff");
+        frame.save_tiff16(&tiff)?;
+        println!("saved {} and {}", png.display(), tiff.display());
     }
+    Ok(())
+}
 
-    pub fn config_write(mut self, endpoint: EndpointDescriptor<'a>) -> Self {
-        self.config_write = Some(endpoint);
-        self
+/// The unit's calibration, cached between runs, or with `use_default` the typical
+/// constants of [`Calibration::default_flir_one`].
+fn calibration(flir: &mut FlirOne, use_default: bool) -> Result<Calibration, Box<dyn Error>> {
+    if use_default {
+        return Ok(Calibration::default_flir_one());
     }
-
-    pub fn frame_read(mut self, endpoint: EndpointDescriptor<'a>) -> Self {
-        self.frame_read = Some(endpoint);
-        self
+    match flir.calibration_cached(&env::temp_dir().join("flir")) {
+        Err(e @ FlirError::NoCalibration(_)) => {
+            eprintln!("pass --default-calibration to use typical constants instead");
+            Err(e.into())
+        }
+        result => Ok(result?),
     }
+}
 
-    pub fn frame_write(mut self, endpoint: EndpointDescriptor<'a>) -> Self {
-        self.frame_write = Some(endpoint);
-        self
+fn watch(
+    flir: DeviceHandle<GlobalContext>,
+    default_calibration: bool,
+    unit: TempUnit,
+) -> Result<(), Box<dyn Error>> {
+    let (mut flir, _) = start(flir)?;
+    let calibration = calibration(&mut flir, default_calibration)?;
+    let mut frames = flir.frames().with_calibration(calibration);
+    while let Some(frame) = frames.next() {
+        let image = ThermalImage::from_frame(&frame?)?;
+        let center = image.sample_celsius(
+            (image.width() - 1) as f32 / 2.0,
+            (image.height() - 1) as f32 / 2.0,
+            &calibration,
+        );
+        let stats = frames.session_stats();
+        let (Some(min), Some(max)) = (stats.min(), stats.max()) else {
+            continue;
+        };
+        print!("\x1b[2J\x1b[H");
+        println!(
+            "frames {} at {:.1} fps",
+            stats.frames(),
+            frames.measured_fps()
+        );
+        println!(
+            "min    {} at ({}, {})",
+            unit.format(min.celsius),
+            min.x,
+            min.y
+        );
+        println!(
+            "max    {} at ({}, {})",
+            unit.format(max.celsius),
+            max.x,
+            max.y
+        );
+        println!("center {}", unit.format(center));
     }
+    Ok(())
+}
 
-    pub fn fileio_read(mut self, endpoint: EndpointDescriptor<'a>) -> Self {
-        self.fileio_read = Some(endpoint);
-        self
+fn probe(
+    flir: DeviceHandle<GlobalContext>,
+    x: f32,
+    y: f32,
+    default_calibration: bool,
+    unit: TempUnit,
+) -> Result<(), Box<dyn Error>> {
+    let (mut flir, frame) = start(flir)?;
+    let calibration = calibration(&mut flir, default_calibration)?;
+    let image = ThermalImage::from_frame(&frame)?;
+    if x < 0.0 || y < 0.0 || x > (image.width() - 1) as f32 || y > (image.height() - 1) as f32 {
+        return Err(format!(
+            "({x}, {y}) is outside the {}x{} image",
+            image.width(),
+            image.height()
+        )
+        .into());
     }
+    println!("{}", unit.format(image.sample_celsius(x, y, &calibration)));
+    Ok(())
+}
 
-    pub fn fileio_write(mut self, endpoint: EndpointDescriptor<'a>) -> Self {
-        self.fileio_write = Some(endpoint);
-        self
+fn record(
+    flir: DeviceHandle<GlobalContext>,
+    dir: &Path,
+    count: usize,
+    default_calibration: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (mut flir, _) = start(flir)?;
+
+    let info = flir.device_info()?;
+    let calibration = calibration(&mut flir, default_calibration)?;
+    let gain_mode = flir
+        .get_option("gainMode")
+        .ok()
+        .and_then(|mode| mode.as_str().map(str::to_owned));
+    let mut session = SessionWriter::create(dir, &info, calibration, gain_mode)?;
+    for frame in flir.frames().discard_shutter_frames().take_count(count) {
+        session.write(&frame?)?;
     }
+    session.finish()?;
+    Ok(())
+}
+
+/// Writes recorded frames to a directory as `frame_000000.raw`, ..., each with its
+/// [`FrameStats`] in `frame_000000.json`, and on [`SessionWriter::finish`] a
+/// `manifest.json` with what is needed to reprocess them.
+struct SessionWriter {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+#[derive(Serialize)]
+struct Manifest {
+    serial: Option<String>,
+    firmware: Option<String>,
+    calibration: Calibration,
+    gain_mode: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    started_at: u64,
+    frame_rate: f64,
+    frames: Vec<ManifestFrame>,
+}
+
+#[derive(Serialize)]
+struct ManifestFrame {
+    file: String,
+    stats: String,
+    timestamp: u64,
+}
 
-    pub fn build(self) -> Result<FlirOne<'a>, &'static str> {
-        Ok(FlirOne {
-            handle: self.handle,
-            config: (
-                self.config_read.ok_or("config_read not set")?,
-                self.config_write.ok_or("config_write not set")?,
-            ),
-            frame: (
-                self.frame_read.ok_or("frame_read not set")?,
-                self.frame_write.ok_or("frame_write not set")?,
-            ),
-            fileio: (
-                self.fileio_read.ok_or("fileio_read not set")?,
-                self.fileio_write.ok_or("fileio_write not set")?,
-            ),
-            connected: false,
-            expect_file_data: false,
-            expect_frame_data: false,
+impl SessionWriter {
+    fn create(
+        dir: &Path,
+        info: &DeviceInfo,
+        calibration: Calibration,
+        gain_mode: Option<String>,
+    ) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(dir)?;
+        Ok(SessionWriter {
+            dir: dir.to_owned(),
+            manifest: Manifest {
+                serial: info.strings.serial_number.clone(),
+                firmware: info.firmware_version.clone(),
+                calibration,
+                gain_mode,
+                started_at: unix_millis(SystemTime::now()),
+                frame_rate: 0.0,
+                frames: Vec::new(),
+            },
         })
     }
-}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    set_log_level(rusb::LogLevel::Debug);
-
-    let flir = open_device_with_vid_pid(0x09CB, 0x1996).unwrap();
-    println!("{flir:#?}");
-
-    let mut builder = FlirOneBuilder::new(flir);
-
-    let config = builder.handle.device().active_config_descriptor()?;
-    for interface in config.interfaces() {
-        builder.handle.claim_interface(interface.number())?;
-        for descriptor in interface.descriptors() {
-            for endpoint in descriptor.endpoint_descriptors() {
-                // println!(
-                //     "{:?} - {:?} - {:?} - {:?}",
-                //     endpoint.direction(),
-                //     endpoint.transfer_type(),
-                //     endpoint.sync_type(),
-                //     endpoint.usage_type()
-                // );
-                builder = match endpoint.direction() {
-                    rusb::Direction::In => match endpoint.number() {
-                        1 => builder.config_read(endpoint),
-                        3 => builder.fileio_read(endpoint),
-                        5 => builder.frame_read(endpoint),
-                        n => panic!("invalid endpoint # {n}"),
-                    },
-                    rusb::Direction::Out => match endpoint.number() {
-                        2 => builder.config_write(endpoint),
-                        4 => builder.fileio_write(endpoint),
-                        6 => builder.frame_write(endpoint),
-                        n => panic!("invalid endpoint # {n}"),
-                    },
-                };
+    fn write(&mut self, frame: &Frame) -> Result<(), Box<dyn Error>> {
+        let index = self.manifest.frames.len();
+        let file = format!("frame_{index:06}.raw");
+        fs::write(self.dir.join(&file), frame.to_bytes())?;
+        let stats = format!("frame_{index:06}.json");
+        let manifest = &self.manifest;
+        let frame_stats =
+            FrameStats::compute(frame, &manifest.calibration, manifest.gain_mode.as_deref())?;
+        fs::write(self.dir.join(&stats), frame_stats.to_json()?)?;
+        self.manifest.frames.push(ManifestFrame {
+            file,
+            stats,
+            timestamp: unix_millis(SystemTime::now()),
+        });
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        let frames = &self.manifest.frames;
+        if let (Some(first), Some(last)) = (frames.first(), frames.last()) {
+            let elapsed = (last.timestamp - first.timestamp) as f64 / 1000.0;
+            if elapsed > 0.0 {
+                self.manifest.frame_rate = (frames.len() - 1) as f64 / elapsed;
             }
         }
+        let json = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(self.dir.join("manifest.json"), json)?;
+        Ok(())
     }
+}
 
-    let mut flir = builder.build()?;
-    let mut buf = [0u8; 4096];
-    flir.connect()?;
-    flir.toggle_communication(ProtocolType::FRAME, true)?;
-    println!("{flir:#?}");
-
-    println!("address {}", flir.frame.0.address());
-    flir.handle
-        .read_bulk(flir.config.0.address(), &mut buf, Duration::from_secs(30))?;
-    println!("{buf:?}");
-    let mut frame_buf = [0u8; 131072];
-    flir.handle
-        .read_bulk(flir.frame.0.address(), &mut frame_buf, Duration::from_secs(30))?;
-    println!("{frame_buf:?}");
-    Ok(())
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
 }
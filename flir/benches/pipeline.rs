@@ -0,0 +1,90 @@
+use std::{env, fs, path::Path, process};
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use flir::{
+    endpoints,
+    sink::{FrameSink, RawFileReader, RawFileSink},
+    test_support::{load_fixture, FakeCamera},
+    transport::EndpointInfo,
+    FlirOne, FlirOneBuilder, Frame, FrameHeader, MockTransport, Palette, ThermalImage,
+};
+
+const TRANSFER_SIZE: usize = 16384;
+
+fn mock_camera(frame: &[u8]) -> FlirOne<MockTransport> {
+    let mut transport = MockTransport::new();
+    for chunk in frame.chunks(TRANSFER_SIZE) {
+        transport.push_read(endpoints::FRAME_READ, chunk);
+    }
+    FlirOneBuilder::new(transport)
+        .config_read(EndpointInfo::new(endpoints::CONFIG_READ, 512))
+        .config_write(EndpointInfo::new(endpoints::CONFIG_WRITE, 512))
+        .fileio_read(EndpointInfo::new(endpoints::FILEIO_READ, 512))
+        .fileio_write(EndpointInfo::new(endpoints::FILEIO_WRITE, 512))
+        .frame_read(EndpointInfo::new(endpoints::FRAME_READ, 512))
+        .frame_write(EndpointInfo::new(endpoints::FRAME_WRITE, 512))
+        .build()
+        .unwrap()
+}
+
+fn pipeline(c: &mut Criterion) {
+    let bytes = FakeCamera::frame_bytes(FakeCamera::hotspot(0));
+    let frame = Frame::parse(&bytes).unwrap();
+    let image = ThermalImage::from_frame(&frame).unwrap();
+
+    c.bench_function("FrameHeader::parse", |b| {
+        b.iter(|| FrameHeader::parse(black_box(&bytes)).unwrap())
+    });
+    c.bench_function("ThermalImage::from_frame", |b| {
+        b.iter(|| ThermalImage::from_frame(black_box(&frame)).unwrap())
+    });
+    c.bench_function("ThermalImage::to_png", |b| {
+        b.iter(|| black_box(&image).to_png(Palette::Grayscale).unwrap())
+    });
+    c.bench_function("FlirOne::read_frame", |b| {
+        b.iter_batched(
+            || mock_camera(&bytes),
+            |mut flir| flir.read_frame().unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Writes `frames` copies of `frame` to `path`, returning the file size.
+fn record(path: &Path, frame: &Frame, frames: usize, level: Option<u32>) -> u64 {
+    let mut sink = match level {
+        Some(level) => RawFileSink::create_compressed(path, level).unwrap(),
+        None => RawFileSink::create(path).unwrap(),
+    };
+    for _ in 0..frames {
+        sink.consume(frame).unwrap();
+    }
+    sink.finish().unwrap();
+    fs::metadata(path).unwrap().len()
+}
+
+fn compression(c: &mut Criterion) {
+    const FRAMES: usize = 20;
+    let frame = load_fixture("hotspot");
+    let path = env::temp_dir().join(format!("flir-bench-{}.raw", process::id()));
+
+    let raw = record(&path, &frame, FRAMES, None);
+    for level in [1, 6, 9] {
+        let compressed = record(&path, &frame, FRAMES, Some(level));
+        println!(
+            "zlib level {level}: {compressed} of {raw} bytes ({:.1}%)",
+            100.0 * compressed as f64 / raw as f64
+        );
+        c.bench_function(&format!("RawFileSink level {level}"), |b| {
+            b.iter(|| record(&path, black_box(&frame), FRAMES, Some(level)))
+        });
+    }
+    assert_eq!(RawFileReader::open(&path).unwrap().count(), FRAMES);
+    c.bench_function("RawFileReader compressed", |b| {
+        b.iter(|| RawFileReader::open(&path).unwrap().count())
+    });
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, pipeline, compression);
+criterion_main!(benches);
@@ -0,0 +1,213 @@
+//! Capture on a background thread, handing frames over through a bounded queue.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    task::Waker,
+    thread::{self, JoinHandle},
+};
+#[cfg(feature = "stream")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{CancelToken, FlirError, FlirOne, Frame, Transport};
+
+/// What the capture thread does when the queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Discard the oldest queued frame, so a slow consumer always gets the freshest one.
+    #[default]
+    DropOldest,
+    /// Stop reading until the consumer catches up.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Frames read from the camera.
+    pub frames: u64,
+    /// Frames discarded under [`QueuePolicy::DropOldest`].
+    pub dropped_backpressure: u64,
+}
+
+struct State {
+    queue: VecDeque<Result<Frame, FlirError>>,
+    stats: CaptureStats,
+    stopping: bool,
+    finished: bool,
+    /// The task polling the `Stream` impl, if it is waiting.
+    waker: Option<Waker>,
+}
+
+impl State {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A [`FlirOne`] reading frames on its own thread, see [`FlirOne::spawn_capture`].
+pub struct ThreadedCapture<T: Transport> {
+    shared: Arc<Shared>,
+    /// Cut short the read in progress on shutdown.
+    cancel: CancelToken,
+    thread: Option<JoinHandle<FlirOne<T>>>,
+}
+
+impl<T: Transport + Send + 'static> ThreadedCapture<T> {
+    pub(crate) fn spawn(mut device: FlirOne<T>, capacity: usize, policy: QueuePolicy) -> Self {
+        let capacity = capacity.max(1);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::with_capacity(capacity),
+                stats: CaptureStats::default(),
+                stopping: false,
+                finished: false,
+                waker: None,
+            }),
+            changed: Condvar::new(),
+        });
+        let producer = Arc::clone(&shared);
+        let cancel = CancelToken::new();
+        let cancelled = cancel.clone();
+        let thread = thread::spawn(move || {
+            loop {
+                if producer.lock().stopping {
+                    break;
+                }
+                let result = device.read_frame_cancellable(&cancelled);
+                // Timeouts just mean nothing arrived; only give up on other errors.
+                let fatal = match &result {
+                    Ok(_) => false,
+                    Err(FlirError::Cancelled) => break,
+                    Err(FlirError::Timeout { .. }) => continue,
+                    Err(_) => true,
+                };
+
+                let mut state = producer.lock();
+                if result.is_ok() {
+                    state.stats.frames += 1;
+                }
+                while state.queue.len() >= capacity && !state.stopping {
+                    match policy {
+                        QueuePolicy::DropOldest => {
+                            state.queue.pop_front();
+                            state.stats.dropped_backpressure += 1;
+                        }
+                        QueuePolicy::Block => {
+                            state = producer
+                                .changed
+                                .wait(state)
+                                .unwrap_or_else(|e| e.into_inner());
+                        }
+                    }
+                }
+                state.queue.push_back(result);
+                state.wake();
+                producer.changed.notify_all();
+                if fatal {
+                    break;
+                }
+            }
+            let mut state = producer.lock();
+            state.finished = true;
+            state.wake();
+            drop(state);
+            producer.changed.notify_all();
+            device
+        });
+        ThreadedCapture {
+            shared,
+            cancel,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl<T: Transport> ThreadedCapture<T> {
+    /// Waits for the next frame. Returns `None` once the thread has stopped, after a read
+    /// error or [`ThreadedCapture::stop`], and the queue is empty.
+    pub fn recv(&self) -> Option<Result<Frame, FlirError>> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(item) = state.queue.pop_front() {
+                self.shared.changed.notify_all();
+                return Some(item);
+            }
+            if state.finished {
+                return None;
+            }
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// The next frame if one is queued.
+    pub fn try_recv(&self) -> Option<Result<Frame, FlirError>> {
+        let item = self.shared.lock().queue.pop_front();
+        if item.is_some() {
+            self.shared.changed.notify_all();
+        }
+        item
+    }
+
+    pub fn stats(&self) -> CaptureStats {
+        self.shared.lock().stats
+    }
+
+    /// Stops the thread and hands the device back. A read in progress is cancelled, so
+    /// this returns within about a tenth of a second even if the camera has gone quiet.
+    pub fn stop(mut self) -> Option<FlirOne<T>> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Option<FlirOne<T>> {
+        self.shared.lock().stopping = true;
+        self.cancel.cancel();
+        self.shared.changed.notify_all();
+        self.thread.take()?.join().ok()
+    }
+}
+
+/// Frames as an async stream, for use from an executor: reading stays on the capture
+/// thread, so polling never blocks.
+#[cfg(feature = "stream")]
+impl<T: Transport> futures_core::Stream for ThreadedCapture<T> {
+    type Item = Result<Frame, FlirError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.shared.lock();
+        if let Some(item) = state.queue.pop_front() {
+            self.shared.changed.notify_all();
+            return Poll::Ready(Some(item));
+        }
+        if state.finished {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T: Transport> Drop for ThreadedCapture<T> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
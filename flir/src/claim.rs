@@ -0,0 +1,152 @@
+use rusb::{DeviceHandle, Direction, TransferType, UsbContext};
+
+use crate::{endpoints, transport::EndpointInfo, FlirError};
+
+const VENDOR_SPECIFIC_CLASS: u8 = 0xff;
+
+/// What [`FlirOne::claim_and_configure`](crate::FlirOne::claim_and_configure) claimed on a
+/// device, and the endpoints it found on those interfaces.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClaimedInterfaces {
+    pub interfaces: Vec<u8>,
+    /// Interfaces whose kernel driver was detached to claim them.
+    pub detached: Vec<u8>,
+    pub config_read: Option<EndpointInfo>,
+    pub config_write: Option<EndpointInfo>,
+    pub fileio_read: Option<EndpointInfo>,
+    pub fileio_write: Option<EndpointInfo>,
+    pub frame_read: Option<EndpointInfo>,
+    pub frame_write: Option<EndpointInfo>,
+    /// Endpoints at none of the addresses in [`endpoints`](crate::endpoints). They are left
+    /// alone; the protocols only ever use the six known ones.
+    pub unmapped: Vec<EndpointInfo>,
+}
+
+impl ClaimedInterfaces {
+    /// Claims the interfaces of the active configuration, or only those in `only`,
+    /// detaching kernel drivers where needed.
+    pub(crate) fn claim<C: UsbContext>(
+        handle: &mut DeviceHandle<C>,
+        only: Option<&[u8]>,
+    ) -> Result<Self, FlirError> {
+        let mut claimed = ClaimedInterfaces::default();
+        let config = handle.device().active_config_descriptor()?;
+        for interface in config.interfaces() {
+            let number = interface.number();
+            if only.is_some_and(|only| !only.contains(&number)) {
+                continue;
+            }
+            // Not every platform can tell; assume there is nothing to detach there.
+            if handle.kernel_driver_active(number).unwrap_or(false) {
+                handle.detach_kernel_driver(number)?;
+                claimed.detached.push(number);
+            }
+            handle.claim_interface(number)?;
+            claimed.interfaces.push(number);
+
+            for descriptor in interface.descriptors() {
+                for endpoint in descriptor.endpoint_descriptors() {
+                    let info = EndpointInfo::from(endpoint);
+                    let slot = match info.address {
+                        endpoints::CONFIG_READ => &mut claimed.config_read,
+                        endpoints::CONFIG_WRITE => &mut claimed.config_write,
+                        endpoints::FILEIO_READ => &mut claimed.fileio_read,
+                        endpoints::FILEIO_WRITE => &mut claimed.fileio_write,
+                        endpoints::FRAME_READ => &mut claimed.frame_read,
+                        endpoints::FRAME_WRITE => &mut claimed.frame_write,
+                        _ => {
+                            claimed.unmapped.push(info);
+                            continue;
+                        }
+                    };
+                    *slot = Some(info);
+                }
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Like [`ClaimedInterfaces::claim`], but assigns the endpoints by layout instead of
+    /// by address: the first three vendor-specific interfaces with a bulk IN and a bulk
+    /// OUT endpoint carry CONFIG, FILEIO and FRAME, in interface order. Fails with
+    /// [`FlirError::EndpointLayout`] if there are fewer than three.
+    pub(crate) fn claim_auto<C: UsbContext>(
+        handle: &mut DeviceHandle<C>,
+        only: Option<&[u8]>,
+    ) -> Result<Self, FlirError> {
+        let claimed = ClaimedInterfaces::claim(handle, only)?;
+        let mut detected = ClaimedInterfaces {
+            interfaces: claimed.interfaces,
+            detached: claimed.detached,
+            ..ClaimedInterfaces::default()
+        };
+        let config = handle.device().active_config_descriptor()?;
+        let mut pairs = Vec::new();
+        for interface in config.interfaces() {
+            if !detected.interfaces.contains(&interface.number()) {
+                continue;
+            }
+            let Some(descriptor) = interface.descriptors().next() else {
+                continue;
+            };
+            let mut endpoints: Vec<_> = descriptor
+                .endpoint_descriptors()
+                .map(|endpoint| {
+                    (
+                        endpoint.direction(),
+                        endpoint.transfer_type(),
+                        EndpointInfo::from(endpoint),
+                    )
+                })
+                .collect();
+            let mut take = |direction| {
+                let at = endpoints
+                    .iter()
+                    .position(|&(d, kind, _)| d == direction && kind == TransferType::Bulk)?;
+                Some(endpoints.remove(at).2)
+            };
+            let pair = (take(Direction::In), take(Direction::Out));
+            match pair {
+                (Some(read), Some(write)) if descriptor.class_code() == VENDOR_SPECIFIC_CLASS => {
+                    pairs.push((read, write));
+                }
+                (read, write) => detected.unmapped.extend(read.into_iter().chain(write)),
+            }
+            detected
+                .unmapped
+                .extend(endpoints.into_iter().map(|(_, _, info)| info));
+        }
+
+        if pairs.len() < 3 {
+            return Err(FlirError::EndpointLayout(format!(
+                "expected 3 vendor-specific interfaces with bulk IN and OUT endpoints, found {}",
+                pairs.len()
+            )));
+        }
+        let mut pairs = pairs.into_iter();
+        for (read, write) in [
+            (&mut detected.config_read, &mut detected.config_write),
+            (&mut detected.fileio_read, &mut detected.fileio_write),
+            (&mut detected.frame_read, &mut detected.frame_write),
+        ] {
+            let (r, w) = pairs.next().unwrap();
+            *read = Some(r);
+            *write = Some(w);
+        }
+        detected
+            .unmapped
+            .extend(pairs.flat_map(|(read, write)| [read, write]));
+        Ok(detected)
+    }
+
+    /// Releases the claimed interfaces and hands detached ones back to their kernel driver.
+    pub fn release<C: UsbContext>(&self, handle: &mut DeviceHandle<C>) -> Result<(), FlirError> {
+        for &number in &self.interfaces {
+            handle.release_interface(number)?;
+        }
+        for &number in &self.detached {
+            handle.attach_kernel_driver(number)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+//! Settings sent over the CONFIG endpoint.
+
+use std::time::{Duration, Instant};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{transport::Transport, CameraStatus, FlirError, FlirOne};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Measurement range, selected through the camera's gain mode.
+///
+/// | model              | supported presets                               |
+/// |--------------------|-------------------------------------------------|
+/// | FLIR One Gen 3     | `High` (-20 °C to 120 °C)                       |
+/// | FLIR One Pro LT    | `High` (-20 °C to 120 °C)                       |
+/// | FLIR One Pro       | `High` (-20 °C to 120 °C), `Low` (up to 400 °C), `Auto` |
+///
+/// Presets a model does not support are rejected by the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangePreset {
+    /// High gain: best sensitivity, narrow span.
+    High,
+    /// Low gain: wide span for hot targets such as soldering irons or exhausts.
+    Low,
+    /// Let the camera switch between the two depending on the scene.
+    Auto,
+}
+
+impl RangePreset {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            RangePreset::High => "high",
+            RangePreset::Low => "low",
+            RangePreset::Auto => "auto",
+        }
+    }
+}
+
+/// Type of value an option takes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionKind {
+    Bool,
+    Integer,
+    Float,
+    String,
+    /// One of a fixed set of strings.
+    Choice(Vec<String>),
+}
+
+/// A settable option as the camera describes it, see
+/// [`FlirOne::list_options`](crate::FlirOne::list_options).
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionDescriptor {
+    pub key: String,
+    pub kind: OptionKind,
+    /// Inclusive bounds of numeric options, where the camera gives them.
+    pub range: Option<(f64, f64)>,
+}
+
+impl OptionDescriptor {
+    /// Parses one entry of a `listOptions` reply, e.g.
+    /// `{"key": "gainMode", "type": "enum", "values": ["high", "low", "auto"]}` or
+    /// `{"key": "emissivity", "type": "float", "min": 0.1, "max": 1.0}`.
+    pub(crate) fn from_json(json: &Value) -> Result<Self, FlirError> {
+        let invalid = || FlirError::Protocol(format!("invalid option descriptor {json}"));
+        let key = json
+            .get("key")
+            .and_then(Value::as_str)
+            .ok_or_else(invalid)?;
+        let kind = match json
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(invalid)?
+        {
+            "bool" | "boolean" => OptionKind::Bool,
+            "int" | "integer" => OptionKind::Integer,
+            "float" | "double" | "number" => OptionKind::Float,
+            "string" => OptionKind::String,
+            "enum" => OptionKind::Choice(
+                json.get("values")
+                    .and_then(Value::as_array)
+                    .ok_or_else(invalid)?
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_owned))
+                    .collect(),
+            ),
+            other => {
+                return Err(FlirError::Protocol(format!(
+                    "option {key} has unknown type {other}"
+                )))
+            }
+        };
+        let bound = |name| json.get(name).and_then(Value::as_f64);
+        Ok(OptionDescriptor {
+            key: key.to_owned(),
+            kind,
+            range: bound("min").zip(bound("max")),
+        })
+    }
+}
+
+/// A scene preset bundling settings for a kind of subject, e.g. `"building"` or
+/// `"electrical"`. Settings the camera does not list with the preset are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenePreset {
+    pub name: String,
+    pub emissivity: Option<f32>,
+    pub gain_mode: Option<String>,
+    pub palette: Option<String>,
+}
+
+impl ScenePreset {
+    /// Parses the presets of the `scenePreset` entry of a `listOptions` reply: either
+    /// `"presets": [{"name": "building", "emissivity": 0.95, ...}]` or, from firmware that
+    /// only names them, an `enum` entry's `values`.
+    pub(crate) fn from_option_json(json: &Value) -> Result<Vec<Self>, FlirError> {
+        let invalid = || FlirError::Protocol(format!("invalid scene presets {json}"));
+        if let Some(presets) = json.get("presets").and_then(Value::as_array) {
+            return presets
+                .iter()
+                .map(|preset| {
+                    let text = |key| preset.get(key).and_then(Value::as_str).map(str::to_owned);
+                    Ok(ScenePreset {
+                        name: text("name").ok_or_else(invalid)?,
+                        emissivity: preset
+                            .get("emissivity")
+                            .and_then(Value::as_f64)
+                            .map(|e| e as f32),
+                        gain_mode: text("gainMode"),
+                        palette: text("palette"),
+                    })
+                })
+                .collect();
+        }
+        match OptionDescriptor::from_json(json)?.kind {
+            OptionKind::Choice(names) => Ok(names
+                .into_iter()
+                .map(|name| ScenePreset {
+                    name,
+                    emissivity: None,
+                    gain_mode: None,
+                    palette: None,
+                })
+                .collect()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Battery charge as the camera reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatteryStatus {
+    /// Charge from 0 to 100.
+    pub percentage: u8,
+    pub charging: bool,
+}
+
+impl BatteryStatus {
+    /// Folds a periodic battery message into `current`, e.g.
+    /// `{"type": "batteryVoltageUpdate", "data": {"percentage": 80}}` or
+    /// `{"type": "batteryChargingStateUpdate", "data": {"chargingState": "noCharging"}}`.
+    /// Other messages leave it as is. Until a percentage has been seen there is nothing
+    /// to report.
+    pub(crate) fn update(
+        current: Option<BatteryStatus>,
+        status: &CameraStatus,
+    ) -> Option<BatteryStatus> {
+        if !status.kind.starts_with("battery") {
+            return current;
+        }
+        let data = &status.data;
+        let percentage = data
+            .get("percentage")
+            .and_then(Value::as_u64)
+            .map(|p| p.min(100) as u8)
+            .or(current.map(|c| c.percentage))?;
+        let charging = match (data.get("charging"), data.get("chargingState")) {
+            (Some(Value::Bool(charging)), _) => *charging,
+            (_, Some(Value::String(state))) => {
+                !matches!(state.as_str(), "noCharging" | "notCharging" | "discharging")
+            }
+            _ => current.is_some_and(|c| c.charging),
+        };
+        Some(BatteryStatus {
+            percentage,
+            charging,
+        })
+    }
+}
+
+/// What [`ConfigChannel::shutter_control`] asks the shutter to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShutterAction {
+    Open,
+    Close,
+    /// Close the shutter for a flat-field correction (NUC) and open it again.
+    #[serde(rename = "ffc")]
+    Calibrate,
+}
+
+#[derive(Serialize)]
+struct SetPalette<'a> {
+    palette: &'a str,
+}
+
+#[derive(Serialize)]
+struct ShutterControl {
+    action: ShutterAction,
+}
+
+/// Typed commands on the CONFIG endpoint, see [`FlirOne::config_channel`]. Each method
+/// sends one JSON request and decodes the camera's reply with serde.
+pub struct ConfigChannel<'d, T: Transport> {
+    pub(crate) device: &'d mut FlirOne<T>,
+    pub(crate) read: u8,
+    pub(crate) write: u8,
+}
+
+impl<T: Transport> ConfigChannel<'_, T> {
+    /// Round-trip time of an empty request, to check the camera is responsive.
+    pub fn ping(&mut self) -> Result<Duration, FlirError> {
+        let start = Instant::now();
+        self.call::<Value>("ping", ())?;
+        Ok(start.elapsed())
+    }
+
+    pub fn get_battery(&mut self) -> Result<BatteryStatus, FlirError> {
+        self.call("getBattery", ())
+    }
+
+    /// Selects the palette the camera renders with, e.g. for the images it stores. Names
+    /// are the camera's own, such as those in
+    /// [`ScenePreset::palette`](crate::config::ScenePreset::palette).
+    pub fn set_palette(&mut self, palette: &str) -> Result<(), FlirError> {
+        self.call::<Value>("setPalette", SetPalette { palette })?;
+        Ok(())
+    }
+
+    pub fn shutter_control(&mut self, action: ShutterAction) -> Result<(), FlirError> {
+        self.call::<Value>("shutterControl", ShutterControl { action })?;
+        Ok(())
+    }
+
+    /// Sends `{"type": kind, "data": data}` and decodes the reply's `data`.
+    pub fn call<R: DeserializeOwned>(
+        &mut self,
+        kind: &str,
+        data: impl Serialize,
+    ) -> Result<R, FlirError> {
+        let data = match serde_json::to_value(data)? {
+            Value::Null => Value::Object(Default::default()),
+            data => data,
+        };
+        let (reply, _) = self
+            .device
+            .request(self.read, self.write, kind, data, TIMEOUT)?;
+        serde_json::from_value(reply)
+            .map_err(|e| FlirError::Protocol(format!("unexpected {kind} reply: {e}")))
+    }
+}
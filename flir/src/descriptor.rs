@@ -0,0 +1,278 @@
+//! USB descriptors: the string descriptors identifying a device and its full descriptor tree.
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use rusb::{DeviceHandle, Direction, TransferType, UsbContext};
+
+use crate::FlirError;
+
+/// English (US), used when the device does not list the languages it supports.
+pub const DEFAULT_LANGUAGE: u16 = 0x0409;
+
+const STRING_TIMEOUT: Duration = Duration::from_secs(1);
+const GET_DESCRIPTOR: u8 = 0x06;
+const STRING_DESCRIPTOR: u16 = 0x03;
+
+/// The string descriptors of a device, read in the first language it supports.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringDescriptors {
+    pub language: u16,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial_number: Option<String>,
+}
+
+impl StringDescriptors {
+    pub(crate) fn read<C: UsbContext>(handle: &DeviceHandle<C>) -> Result<Self, FlirError> {
+        let descriptor = handle.device().device_descriptor()?;
+        let language = language(handle);
+        Ok(StringDescriptors {
+            language,
+            manufacturer: read_string(handle, language, descriptor.manufacturer_string_index())?,
+            product: read_string(handle, language, descriptor.product_string_index())?,
+            serial_number: read_string(handle, language, descriptor.serial_number_string_index())?,
+        })
+    }
+}
+
+/// Identification of the device a [`FlirOne`](crate::FlirOne) is talking to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub strings: StringDescriptors,
+    /// The camera clock, if it answered when asked.
+    pub device_time: Option<SystemTime>,
+    /// Versions and revision as the camera reports them over CONFIG, where it does.
+    pub firmware_version: Option<String>,
+    pub software_version: Option<String>,
+    pub hardware_revision: Option<String>,
+}
+
+/// The full descriptor tree of a device, see [`FlirOne::describe`](crate::FlirOne::describe).
+/// Its [`Display`](fmt::Display) output is an indented tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceDescription {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub usb_version: String,
+    pub device_version: String,
+    pub class: u8,
+    pub strings: StringDescriptors,
+    pub configs: Vec<ConfigDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDescription {
+    pub number: u8,
+    pub max_power_ma: u16,
+    pub self_powered: bool,
+    pub interfaces: Vec<InterfaceDescription>,
+}
+
+/// One configuration of a device, see
+/// [`FlirOne::list_configurations`](crate::FlirOne::list_configurations).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigSummary {
+    pub number: u8,
+    pub num_interfaces: u8,
+    pub max_power_ma: u16,
+    pub self_powered: bool,
+    /// Whether this is the configuration the device currently uses.
+    pub active: bool,
+}
+
+impl ConfigSummary {
+    pub(crate) fn read_all<C: UsbContext>(
+        handle: &DeviceHandle<C>,
+    ) -> Result<Vec<ConfigSummary>, FlirError> {
+        let device = handle.device();
+        let active = handle.active_configuration()?;
+        let mut configs = Vec::new();
+        for index in 0..device.device_descriptor()?.num_configurations() {
+            let config = device.config_descriptor(index)?;
+            configs.push(ConfigSummary {
+                number: config.number(),
+                num_interfaces: config.num_interfaces(),
+                max_power_ma: config.max_power(),
+                self_powered: config.self_powered(),
+                active: config.number() == active,
+            });
+        }
+        Ok(configs)
+    }
+}
+
+/// One alternate setting of an interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceDescription {
+    pub number: u8,
+    pub setting: u8,
+    pub class: u8,
+    pub sub_class: u8,
+    pub protocol: u8,
+    pub endpoints: Vec<EndpointDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointDescription {
+    pub address: u8,
+    pub direction: Direction,
+    pub transfer_type: TransferType,
+    pub max_packet_size: u16,
+    pub interval: u8,
+}
+
+impl DeviceDescription {
+    pub(crate) fn read<C: UsbContext>(handle: &DeviceHandle<C>) -> Result<Self, FlirError> {
+        let device = handle.device();
+        let descriptor = device.device_descriptor()?;
+        let mut configs = Vec::new();
+        for index in 0..descriptor.num_configurations() {
+            let config = device.config_descriptor(index)?;
+            let mut interfaces = Vec::new();
+            for interface in config.interfaces() {
+                for setting in interface.descriptors() {
+                    interfaces.push(InterfaceDescription {
+                        number: setting.interface_number(),
+                        setting: setting.setting_number(),
+                        class: setting.class_code(),
+                        sub_class: setting.sub_class_code(),
+                        protocol: setting.protocol_code(),
+                        endpoints: setting
+                            .endpoint_descriptors()
+                            .map(|endpoint| EndpointDescription {
+                                address: endpoint.address(),
+                                direction: endpoint.direction(),
+                                transfer_type: endpoint.transfer_type(),
+                                max_packet_size: endpoint.max_packet_size(),
+                                interval: endpoint.interval(),
+                            })
+                            .collect(),
+                    });
+                }
+            }
+            configs.push(ConfigDescription {
+                number: config.number(),
+                max_power_ma: config.max_power(),
+                self_powered: config.self_powered(),
+                interfaces,
+            });
+        }
+        Ok(DeviceDescription {
+            vendor_id: descriptor.vendor_id(),
+            product_id: descriptor.product_id(),
+            usb_version: descriptor.usb_version().to_string(),
+            device_version: descriptor.device_version().to_string(),
+            class: descriptor.class_code(),
+            strings: StringDescriptors::read(handle)?,
+            configs,
+        })
+    }
+}
+
+impl fmt::Display for DeviceDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unknown = || "?".to_owned();
+        writeln!(
+            f,
+            "device {:04x}:{:04x} usb {} version {} class {:#04x}",
+            self.vendor_id, self.product_id, self.usb_version, self.device_version, self.class
+        )?;
+        writeln!(
+            f,
+            "  manufacturer {:?} product {:?} serial {:?} (language {:#06x})",
+            self.strings.manufacturer.clone().unwrap_or_else(unknown),
+            self.strings.product.clone().unwrap_or_else(unknown),
+            self.strings.serial_number.clone().unwrap_or_else(unknown),
+            self.strings.language
+        )?;
+        for config in &self.configs {
+            writeln!(
+                f,
+                "  config {} max power {} mA{}",
+                config.number,
+                config.max_power_ma,
+                if config.self_powered {
+                    " self-powered"
+                } else {
+                    ""
+                }
+            )?;
+            for interface in &config.interfaces {
+                writeln!(
+                    f,
+                    "    interface {} setting {} class {:#04x}/{:#04x}/{:#04x}",
+                    interface.number,
+                    interface.setting,
+                    interface.class,
+                    interface.sub_class,
+                    interface.protocol
+                )?;
+                for endpoint in &interface.endpoints {
+                    writeln!(
+                        f,
+                        "      endpoint {:#04x} {:?} {:?} max packet {} interval {}",
+                        endpoint.address,
+                        endpoint.direction,
+                        endpoint.transfer_type,
+                        endpoint.max_packet_size,
+                        endpoint.interval
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The first language in string descriptor 0, or [`DEFAULT_LANGUAGE`] if the device
+/// cannot report one.
+fn language<C: UsbContext>(handle: &DeviceHandle<C>) -> u16 {
+    match handle.read_languages(STRING_TIMEOUT) {
+        Ok(languages) => languages
+            .first()
+            .map_or(DEFAULT_LANGUAGE, |language| language.lang_id()),
+        Err(_) => DEFAULT_LANGUAGE,
+    }
+}
+
+/// rusb only reads strings in a language it got from the device, so this issues the
+/// GET_DESCRIPTOR request itself to be able to fall back to [`DEFAULT_LANGUAGE`].
+fn read_string<C: UsbContext>(
+    handle: &DeviceHandle<C>,
+    language: u16,
+    index: Option<u8>,
+) -> Result<Option<String>, FlirError> {
+    let Some(index) = index else {
+        return Ok(None);
+    };
+    let mut buf = [0u8; 255];
+    let n = handle.read_control(
+        rusb::request_type(
+            rusb::Direction::In,
+            rusb::RequestType::Standard,
+            rusb::Recipient::Device,
+        ),
+        GET_DESCRIPTOR,
+        STRING_DESCRIPTOR << 8 | u16::from(index),
+        language,
+        &mut buf,
+        STRING_TIMEOUT,
+    )?;
+    if n < 2 || n % 2 != 0 || usize::from(buf[0]) != n {
+        return Err(FlirError::InvalidFormat(format!(
+            "string descriptor {index} is malformed"
+        )));
+    }
+    let units: Vec<u16> = buf[2..n]
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16(&units)
+        .map(Some)
+        .map_err(|_| FlirError::InvalidFormat(format!("string descriptor {index} is not UTF-16")))
+}
@@ -0,0 +1,1481 @@
+use std::{
+    fmt, fs,
+    path::Path,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use rusb::{DeviceHandle, GlobalContext, LogLevel, UsbContext};
+use serde_json::{json, Value};
+
+#[cfg(feature = "transfer-log")]
+use crate::transfer_log::{TransferKind, TransferLog};
+use crate::{
+    capture::{QueuePolicy, ThreadedCapture},
+    claim::ClaimedInterfaces,
+    config::{BatteryStatus, ConfigChannel, OptionDescriptor, RangePreset, ScenePreset},
+    descriptor::{ConfigSummary, DeviceDescription, DeviceInfo, StringDescriptors},
+    endpoints::{self, AddressMismatch},
+    fff,
+    fileio::{FileSystem, STORED_IMAGES_DIR},
+    frame::{Frame, FrameParser, HEADER_LEN},
+    message::{self, ConfigCodec},
+    retry::RetryPolicy,
+    sink::FrameSink,
+    stream::FrameStream,
+    transport::{EndpointInfo, Transport},
+    Calibration, CameraStatus, CancelToken, FlirError, Fov, FrameStats, Orientation, ThermalImage,
+};
+
+pub const FLIR_VENDOR_ID: u16 = 0x09cb;
+/// Product IDs of the FLIR One in camera mode.
+pub const FLIR_ONE_PRODUCT_IDS: &[u16] = &[0x1996];
+
+const JPEG_SOI: [u8; 2] = [0xff, 0xd8];
+/// Default for [`FlirOneBuilder::config_read_size`].
+pub const CONFIG_READ_SIZE: usize = 16384;
+const STATUS_POLL_TIMEOUT: Duration = Duration::from_millis(500);
+const POWER_SAVE_OPTION: &str = "powerSave";
+const FIRMWARE_VERSION_OPTION: &str = "firmwareVersion";
+const SOFTWARE_VERSION_OPTION: &str = "softwareVersion";
+const HARDWARE_REVISION_OPTION: &str = "hardwareRevision";
+const TIME_OPTION: &str = "time";
+const SCENE_PRESET_OPTION: &str = "scenePreset";
+const LED_OPTION: &str = "led";
+const ORIENTATION_OPTION: &str = "orientation";
+const FIELD_OF_VIEW_OPTION: &str = "fieldOfView";
+const FRAME_RATE_OPTION: &str = "frameRate";
+/// Data that fits the default control endpoint packet size.
+const MAX_CONTROL_DATA: usize = 64;
+const CONFIG_TIMEOUT: Duration = Duration::from_secs(2);
+/// Default for [`FlirOneBuilder::max_frame_read`]. A whole frame, about 40 KB of thermal
+/// data plus a JPEG of typically 20-60 KB, fits in one read of this size.
+pub const MAX_FRAME_SIZE: usize = 131072;
+// Consecutive zero-length reads tolerated, and the pause after each.
+const ZERO_LENGTH_READ_LIMIT: u32 = 20;
+const ZERO_LENGTH_READ_BACKOFF: Duration = Duration::from_millis(10);
+const FRAME_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest a cancellable read blocks before checking its token.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+const FLUSH_TIMEOUT: Duration = Duration::from_millis(10);
+/// Reads per endpoint after which [`FlirOne::flush_endpoints`] gives up on a camera that
+/// keeps sending.
+const FLUSH_MAX_READS: usize = 64;
+/// How long [`FlirOne::connect_and_wait`] waits for the first usable frame.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolType {
+    CONFIG,
+    FILEIO,
+    FRAME,
+}
+
+impl ProtocolType {
+    /// The `wIndex` selecting this protocol in the start/stop control transfer.
+    pub fn index(&self) -> u16 {
+        match self {
+            ProtocolType::CONFIG => 0,
+            ProtocolType::FILEIO => 1,
+            ProtocolType::FRAME => 2,
+        }
+    }
+}
+
+impl fmt::Display for ProtocolType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProtocolType::CONFIG => "CONFIG",
+            ProtocolType::FILEIO => "FILEIO",
+            ProtocolType::FRAME => "FRAME",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct FlirOne<T: Transport = DeviceHandle<GlobalContext>> {
+    handle: T,
+    config: (Option<EndpointInfo>, Option<EndpointInfo>),
+    frame: (Option<EndpointInfo>, Option<EndpointInfo>),
+    fileio: (Option<EndpointInfo>, Option<EndpointInfo>),
+    connected: bool,
+    config_started: bool,
+    expect_file_data: bool,
+    expect_frame_data: bool,
+    paused: bool,
+    power_save: bool,
+    frame_parser: FrameParser,
+    config_codec: ConfigCodec,
+    fileio_codec: ConfigCodec,
+    config_read_size: usize,
+    max_frame_read: usize,
+    calibration: Option<Calibration>,
+    last_frame: Option<Frame>,
+    /// From the last battery messages seen on CONFIG.
+    battery: Option<BatteryStatus>,
+    /// Released again on drop.
+    interfaces: Vec<u8>,
+    /// Handed back to their kernel driver on drop.
+    detached: Vec<u8>,
+    retry_policy: RetryPolicy,
+    #[cfg(feature = "transfer-log")]
+    transfer_log: Option<TransferLog>,
+}
+
+impl FlirOne {
+    /// Opens the first FLIR One found, claims its interfaces and finds its endpoints with
+    /// [`FlirOneBuilder::auto_detect`].
+    pub fn open() -> Result<FlirOne, FlirError> {
+        let handle = FLIR_ONE_PRODUCT_IDS
+            .iter()
+            .find_map(|&product_id| rusb::open_device_with_vid_pid(FLIR_VENDOR_ID, product_id))
+            .ok_or(FlirError::DeviceNotFound)?;
+        let mut builder = FlirOneBuilder::new(handle);
+        builder.auto_detect()?;
+        builder.build()
+    }
+
+    /// Claims every interface of the handle's active configuration and collects the
+    /// protocol endpoints on them, ready for [`FlirOneBuilder::claimed`].
+    pub fn claim_and_configure<C: UsbContext>(
+        handle: &mut DeviceHandle<C>,
+    ) -> Result<ClaimedInterfaces, FlirError> {
+        ClaimedInterfaces::claim(handle, None)
+    }
+
+    /// Reads the device, configuration, interface and endpoint descriptors of `handle`,
+    /// whether or not it is a FLIR One.
+    pub fn describe<C: UsbContext>(
+        handle: &DeviceHandle<C>,
+    ) -> Result<DeviceDescription, FlirError> {
+        DeviceDescription::read(handle)
+    }
+
+    /// The configurations `handle` offers, e.g. to pick one for
+    /// [`FlirOneBuilder::use_configuration`] on cameras that also present a storage
+    /// configuration.
+    pub fn list_configurations<C: UsbContext>(
+        handle: &DeviceHandle<C>,
+    ) -> Result<Vec<ConfigSummary>, FlirError> {
+        ConfigSummary::read_all(handle)
+    }
+
+    /// Serial numbers of the FLIR Ones attached to the system. Cameras that cannot be
+    /// opened, e.g. for lack of permissions, are skipped.
+    pub fn list_serials() -> Result<Vec<String>, FlirError> {
+        let mut serials = Vec::new();
+        for device in rusb::devices()?.iter() {
+            let descriptor = device.device_descriptor()?;
+            if descriptor.vendor_id() != FLIR_VENDOR_ID
+                || !FLIR_ONE_PRODUCT_IDS.contains(&descriptor.product_id())
+            {
+                continue;
+            }
+            let Ok(handle) = device.open() else {
+                continue;
+            };
+            if let Some(serial) = StringDescriptors::read(&handle)?.serial_number {
+                serials.push(serial);
+            }
+        }
+        Ok(serials)
+    }
+}
+
+impl<C: UsbContext> FlirOne<DeviceHandle<C>> {
+    /// Opens the first camera with `product_id`, one of [`FLIR_ONE_PRODUCT_IDS`], in the
+    /// libusb context `ctx` rather than the global one, and claims its interfaces.
+    /// Applications embedding other USB libraries can keep the camera in a context of
+    /// its own this way.
+    pub fn open_with_context(ctx: &C, product_id: u16) -> Result<Self, FlirError> {
+        let handle = ctx
+            .open_device_with_vid_pid(FLIR_VENDOR_ID, product_id)
+            .ok_or(FlirError::DeviceNotFound)?;
+        let mut builder = FlirOneBuilder::new(handle);
+        builder.claim_and_configure()?;
+        builder.build()
+    }
+}
+
+impl<T: Transport> FlirOne<T> {
+    /// Starts or stops one protocol. Starting FRAME before CONFIG leaves the camera
+    /// silent, so that is refused with [`FlirError::ConfigNotStarted`];
+    /// [`FlirOne::connect`] starts CONFIG.
+    pub fn toggle_communication(
+        &mut self,
+        protocol_type: ProtocolType,
+        start: bool,
+    ) -> Result<(), FlirError> {
+        if protocol_type == ProtocolType::FRAME && start && !self.config_started {
+            return Err(FlirError::ConfigNotStarted);
+        }
+        let control_cmd = if start { 1 } else { 0 };
+        self.send_control(
+            0x1,
+            11,
+            control_cmd,
+            protocol_type.index(),
+            &Vec::new(),
+            Duration::from_secs(1),
+        )?;
+
+        // Only once the camera has accepted the change, so the flags follow its state.
+        match protocol_type {
+            ProtocolType::CONFIG => self.config_started = start,
+            ProtocolType::FILEIO => self.expect_file_data = start,
+            ProtocolType::FRAME => self.expect_frame_data = start,
+        }
+        Ok(())
+    }
+
+    /// Starts the CONFIG and FILEIO protocols, unless that has been done already. Anything
+    /// left queued on the endpoints by an earlier session is flushed first.
+    pub fn connect(&mut self) -> Result<(), FlirError> {
+        if !self.connected {
+            self.flush_endpoints()?;
+            self.toggle_communication(ProtocolType::CONFIG, true)?;
+            self.toggle_communication(ProtocolType::FILEIO, true)?;
+            // Only once both are running, so a failed connect can be retried.
+            self.connected = true;
+        }
+        Ok(())
+    }
+
+    /// Starts the frame protocol on a connected camera.
+    pub fn start_stream(&mut self) -> Result<(), FlirError> {
+        if !self.connected {
+            return Err(FlirError::NotConnected);
+        }
+        self.toggle_communication(ProtocolType::FRAME, true)?;
+        self.paused = false;
+        Ok(())
+    }
+
+    /// Whether the frame protocol has been started and not stopped since.
+    pub fn is_streaming(&self) -> bool {
+        self.expect_frame_data
+    }
+
+    /// Connects and starts the frame protocol unless that has been done already.
+    pub(crate) fn ensure_streaming(&mut self) -> Result<(), FlirError> {
+        self.connect()?;
+        if !self.expect_frame_data {
+            self.start_stream()?;
+        }
+        Ok(())
+    }
+
+    /// Stops the frame protocol, dropping any partly received frame.
+    pub fn stop_stream(&mut self) -> Result<(), FlirError> {
+        self.toggle_communication(ProtocolType::FRAME, false)?;
+        self.frame_parser = FrameParser::new();
+        Ok(())
+    }
+
+    /// Connects, starts the frame protocol and waits for the camera to be ready, then
+    /// returns the first frame whose thermal plane decodes and that was not taken with
+    /// the shutter closed. Fails with
+    /// [`FlirError::NotReady`] if that takes longer than 30 seconds.
+    pub fn connect_and_wait(&mut self) -> Result<Frame, FlirError> {
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        self.connect()?;
+        self.start_stream()?;
+        self.wait_ready(deadline.saturating_duration_since(Instant::now()))?;
+        loop {
+            if Instant::now() >= deadline {
+                return Err(FlirError::NotReady);
+            }
+            let frame = match self.read_frame() {
+                Ok(frame) => frame,
+                Err(FlirError::Timeout { .. }) => continue,
+                Err(e) => return Err(e),
+            };
+            if ThermalImage::from_frame(&frame).is_ok() && !frame.is_shutter_frame() {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Stops the frame protocol while leaving the interfaces claimed and the other
+    /// protocols running, so [`FlirOne::resume`] is much cheaper than a reconnect.
+    /// Any partly received frame is dropped, as it would be stale by the time frames flow
+    /// again.
+    pub fn pause(&mut self) -> Result<(), FlirError> {
+        if self.connected && !self.paused {
+            self.toggle_communication(ProtocolType::FRAME, false)?;
+            self.paused = true;
+            if self.power_save {
+                self.set_option(POWER_SAVE_OPTION, json!(true))?;
+            }
+        }
+        self.frame_parser = FrameParser::new();
+        Ok(())
+    }
+
+    /// Restarts the frame protocol after [`FlirOne::pause`]. With power saving on, the
+    /// camera is woken first and takes a moment to stream again; [`FlirOne::wait_ready`]
+    /// tells when it is back.
+    pub fn resume(&mut self) -> Result<(), FlirError> {
+        if self.connected && self.paused {
+            self.flush_endpoints()?;
+            if self.power_save {
+                self.set_option(POWER_SAVE_OPTION, json!(false))?;
+            }
+            self.toggle_communication(ProtocolType::FRAME, true)?;
+            self.paused = false;
+        }
+        Ok(())
+    }
+
+    /// Drains whatever the camera still has queued on the read endpoints and forgets any
+    /// partly received frame or message, so the next read starts clean. Each endpoint is
+    /// read until a short read times out, or a bounded number of times. [`FlirOne::connect`]
+    /// and [`FlirOne::resume`] call this; a [`FrameStream::with_watchdog`] restart only
+    /// flushes the FRAME endpoint, keeping CONFIG messages such as battery updates.
+    pub fn flush_endpoints(&mut self) -> Result<(), FlirError> {
+        for endpoint in [self.config.0, self.fileio.0, self.frame.0] {
+            self.drain(endpoint)?;
+        }
+        self.frame_parser = FrameParser::new();
+        self.config_codec = ConfigCodec::new();
+        self.fileio_codec = ConfigCodec::new();
+        Ok(())
+    }
+
+    /// [`FlirOne::flush_endpoints`] for the FRAME endpoint alone.
+    pub(crate) fn flush_frame_endpoint(&mut self) -> Result<(), FlirError> {
+        self.drain(self.frame.0)?;
+        self.frame_parser = FrameParser::new();
+        Ok(())
+    }
+
+    fn drain(&mut self, endpoint: Option<EndpointInfo>) -> Result<(), FlirError> {
+        let Some(endpoint) = endpoint else {
+            return Ok(());
+        };
+        let mut buf = vec![0u8; self.max_frame_read.max(self.config_read_size)];
+        for _ in 0..FLUSH_MAX_READS {
+            match self.poll_bulk(endpoint.address, &mut buf, FLUSH_TIMEOUT) {
+                Ok(0) | Err(FlirError::Usb(rusb::Error::Timeout)) => break,
+                Ok(_) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Lets the camera idle while paused, to save battery between captures. Takes effect
+    /// straight away if the camera is already paused.
+    pub fn set_power_save(&mut self, enabled: bool) -> Result<(), FlirError> {
+        self.power_save = enabled;
+        if self.connected && self.paused {
+            self.set_option(POWER_SAVE_OPTION, json!(enabled))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn handle(&self) -> &T {
+        &self.handle
+    }
+
+    /// IDs and string descriptors of the device, the strings read in the first language
+    /// it supports, and what the camera answers over CONFIG: its clock, firmware and
+    /// software versions and hardware revision. Those it does not answer are `None`.
+    pub fn device_info(&mut self) -> Result<DeviceInfo, FlirError> {
+        let (vendor_id, product_id) = self.handle.device_ids()?;
+        Ok(DeviceInfo {
+            vendor_id,
+            product_id,
+            strings: self.handle.string_descriptors()?,
+            device_time: self.device_time().ok(),
+            firmware_version: self.firmware_version_string().ok(),
+            software_version: self.string_option(SOFTWARE_VERSION_OPTION),
+            hardware_revision: self.string_option(HARDWARE_REVISION_OPTION),
+        })
+    }
+
+    /// The camera clock, which resets when its battery runs flat.
+    pub fn device_time(&mut self) -> Result<SystemTime, FlirError> {
+        let value = self.get_option(TIME_OPTION)?;
+        value
+            .as_f64()
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .map(|elapsed| UNIX_EPOCH + elapsed)
+            .ok_or_else(|| FlirError::Protocol(format!("camera time is not a timestamp: {value}")))
+    }
+
+    /// Sets the camera clock, which stamps stored images and their metadata.
+    pub fn set_time(&mut self, time: SystemTime) -> Result<(), FlirError> {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| FlirError::Protocol("cannot set the camera clock before 1970".to_owned()))?
+            .as_secs_f64();
+        self.set_option(TIME_OPTION, json!(seconds))
+    }
+
+    /// Appends every subsequent control and bulk transfer to `path`, in the format
+    /// described in [`transfer_log`](crate::transfer_log).
+    #[cfg(feature = "transfer-log")]
+    pub fn enable_transfer_log(&mut self, path: &Path) -> Result<(), FlirError> {
+        self.transfer_log = Some(TransferLog::open(path)?);
+        Ok(())
+    }
+
+    #[cfg(feature = "transfer-log")]
+    pub fn disable_transfer_log(&mut self) {
+        self.transfer_log = None;
+    }
+
+    /// Reads whatever the IN endpoint of `protocol_type` has pending.
+    pub fn read(
+        &mut self,
+        protocol_type: ProtocolType,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        let endpoint = match protocol_type {
+            ProtocolType::CONFIG => address(self.config.0, "config_read")?,
+            ProtocolType::FILEIO => address(self.fileio.0, "fileio_read")?,
+            ProtocolType::FRAME => address(self.frame.0, "frame_read")?,
+        };
+        self.read_bulk(endpoint, buf, timeout)
+    }
+
+    /// Reads from the FRAME endpoint until a complete frame has arrived.
+    ///
+    /// If a read times out, the bytes received so far are kept and the next call carries
+    /// on from them; [`FlirError::Timeout`] reports how many are pending.
+    ///
+    /// Some hosts complete reads with zero bytes while the camera has nothing to send;
+    /// after a run of those [`FlirError::NoData`] is returned instead of spinning.
+    ///
+    /// Reads come in two phases, each rounded up to whole packets so the camera never
+    /// sends more than the host asked for (which xHCI reports as babble): first just
+    /// enough for the header, then the rest of the frame as the header announces it.
+    pub fn read_frame(&mut self) -> Result<Frame, FlirError> {
+        self.read_frame_with(FRAME_TIMEOUT, None, false)
+    }
+
+    /// [`FlirOne::read_frame`] with `timeout` for each bulk read. Timeouts are not retried,
+    /// so it gives up after `timeout` without data whatever the retry policy.
+    pub(crate) fn read_frame_timeout(&mut self, timeout: Duration) -> Result<Frame, FlirError> {
+        self.read_frame_with(timeout, None, true)
+    }
+
+    /// [`FlirOne::read_frame`] that gives up with [`FlirError::Cancelled`] soon after
+    /// `cancel` is cancelled, e.g. by a thread shutting down. Bytes already received are
+    /// kept for the next read, as after a timeout.
+    pub fn read_frame_cancellable(&mut self, cancel: &CancelToken) -> Result<Frame, FlirError> {
+        self.read_frame_with(FRAME_TIMEOUT, Some(cancel), true)
+    }
+
+    fn read_frame_with(
+        &mut self,
+        timeout: Duration,
+        cancel: Option<&CancelToken>,
+        poll: bool,
+    ) -> Result<Frame, FlirError> {
+        let frame = Frame::parse(&self.read_raw_frame_with(timeout, cancel, poll)?)?;
+        self.last_frame = Some(frame.clone());
+        Ok(frame)
+    }
+
+    /// The calibration given to [`FlirOneBuilder::calibration`] or
+    /// [`FlirOne::set_calibration`].
+    pub fn calibration(&self) -> Option<&Calibration> {
+        self.calibration.as_ref()
+    }
+
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = Some(calibration);
+    }
+
+    /// [`FrameStats`] of the last frame read, converted with the device's calibration.
+    /// `None` before the first frame.
+    pub fn last_frame_stats(&self) -> Result<Option<FrameStats>, FlirError> {
+        let calibration = self
+            .calibration
+            .as_ref()
+            .ok_or_else(|| FlirError::NoCalibration("none was given to the device".to_owned()))?;
+        self.last_frame
+            .as_ref()
+            .map(|frame| FrameStats::compute(frame, calibration, None))
+            .transpose()
+    }
+
+    /// A complete frame's bytes exactly as the camera sent them, header included, with
+    /// only the magic and section sizes checked. The cheapest way to archive frames, and
+    /// the safest for firmware whose frames [`Frame::parse`] might misread.
+    pub fn read_raw_frame(&mut self) -> Result<Vec<u8>, FlirError> {
+        self.read_raw_frame_timeout(FRAME_TIMEOUT)
+    }
+
+    fn read_raw_frame_timeout(&mut self, timeout: Duration) -> Result<Vec<u8>, FlirError> {
+        self.read_raw_frame_with(timeout, None, false)
+    }
+
+    /// With a `cancel` token, each bulk read is cut to [`CANCEL_POLL`] and the token is
+    /// checked between them; `timeout` then bounds the time without any data. With `poll`,
+    /// bulk reads that time out are not retried.
+    fn read_raw_frame_with(
+        &mut self,
+        timeout: Duration,
+        cancel: Option<&CancelToken>,
+        poll: bool,
+    ) -> Result<Vec<u8>, FlirError> {
+        let chunk_timeout = match cancel {
+            Some(_) => timeout.min(CANCEL_POLL),
+            None => timeout,
+        };
+        let mut last_data = Instant::now();
+        let endpoint = self
+            .frame
+            .0
+            .ok_or(FlirError::EndpointMissing("frame_read"))?;
+        let packet = usize::from(endpoint.max_packet_size.max(1));
+        let max_read = (self.max_frame_read / packet).max(1) * packet;
+        let mut buf = vec![0u8; max_read];
+        let mut empty_reads = 0;
+        loop {
+            if let Some(frame) = self.frame_parser.next_raw_frame()? {
+                return Ok(frame);
+            }
+            if cancel.is_some_and(CancelToken::is_cancelled) {
+                return Err(FlirError::Cancelled);
+            }
+            let wanted = match self.frame_parser.missing() {
+                Some(missing) => missing,
+                None => HEADER_LEN.saturating_sub(self.frame_parser.buffered()),
+            };
+            let size = wanted.max(1).div_ceil(packet) * packet;
+            let read = &mut buf[..size.min(max_read)];
+            let result = if poll {
+                self.poll_bulk(endpoint.address, read, chunk_timeout)
+            } else {
+                self.read_bulk(endpoint.address, read, chunk_timeout)
+            };
+            let n = match result {
+                Ok(n) => n,
+                Err(FlirError::Usb(rusb::Error::Timeout))
+                    if cancel.is_some() && last_data.elapsed() < timeout =>
+                {
+                    continue
+                }
+                Err(FlirError::Usb(rusb::Error::Timeout)) => {
+                    return Err(FlirError::Timeout {
+                        partial_bytes: self.frame_parser.buffered(),
+                    })
+                }
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                empty_reads += 1;
+                if empty_reads > ZERO_LENGTH_READ_LIMIT {
+                    return Err(FlirError::NoData);
+                }
+                thread::sleep(ZERO_LENGTH_READ_BACKOFF);
+                continue;
+            }
+            empty_reads = 0;
+            last_data = Instant::now();
+            self.frame_parser.push(&read[..n]);
+        }
+    }
+
+    /// Feeds frames to `sink`, `count` of them or until reading or the sink fails.
+    pub fn run_stream(
+        &mut self,
+        mut sink: impl FrameSink,
+        count: Option<usize>,
+    ) -> Result<(), FlirError> {
+        let mut taken = 0;
+        while count.is_none_or(|count| taken < count) {
+            sink.consume(&self.read_frame()?)?;
+            taken += 1;
+        }
+        Ok(())
+    }
+
+    /// An iterator over frames from [`FlirOne::read_frame`].
+    pub fn frames(&mut self) -> FrameStream<'_, T> {
+        FrameStream::new(self)
+    }
+
+    /// Sends a `{"type": kind, "data": data}` request and waits for the reply of the same
+    /// type, skipping unrelated messages the camera sends in the meantime. Returns the
+    /// reply's `data` and any binary payload that followed its JSON.
+    pub(crate) fn request(
+        &mut self,
+        read: u8,
+        write: u8,
+        kind: &str,
+        data: Value,
+        timeout: Duration,
+    ) -> Result<(Value, Vec<u8>), FlirError> {
+        self.request_with_payload(read, write, kind, data, &[], timeout)
+    }
+
+    /// [`FlirOne::request`] with binary data sent after the request's JSON.
+    pub(crate) fn request_with_payload(
+        &mut self,
+        read: u8,
+        write: u8,
+        kind: &str,
+        data: Value,
+        payload: &[u8],
+        timeout: Duration,
+    ) -> Result<(Value, Vec<u8>), FlirError> {
+        let json = json!({ "type": kind, "data": data }).to_string();
+        self.write_bulk(write, &message::encode(&json, payload), timeout)?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(FlirError::Timeout { partial_bytes: 0 });
+            }
+            let payload = self.read_message(read, remaining)?;
+            let (reply, rest) = message::split_payload(&payload)?;
+            if reply.get("type").and_then(Value::as_str) != Some(kind) {
+                self.observe(&CameraStatus::from_json(reply));
+                continue;
+            }
+            if let Some(error) = reply.get("error") {
+                return Err(FlirError::Protocol(format!("{kind} failed: {error}")));
+            }
+            let data = reply.get("data").cloned().unwrap_or(Value::Null);
+            return Ok((data, rest.to_vec()));
+        }
+    }
+
+    /// Reads one framed CONFIG/FILEIO message and returns its payload. Bytes read past
+    /// its end are kept for the next call. Gives [`FlirError::Timeout`] if no complete
+    /// message arrives within `timeout`, however the endpoint behaves meanwhile.
+    pub(crate) fn read_message(
+        &mut self,
+        endpoint: u8,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, FlirError> {
+        let packet = [self.config.0, self.fileio.0]
+            .into_iter()
+            .flatten()
+            .find(|info| info.address == endpoint)
+            .map_or(1, |info| usize::from(info.max_packet_size.max(1)));
+        let max_read = (self.config_read_size / packet).max(1) * packet;
+        let mut buf = vec![0u8; max_read];
+        let deadline = Instant::now() + timeout;
+        let mut empty_reads = 0;
+        loop {
+            if let Some(payload) = self.codec(endpoint).next_message()? {
+                return Ok(payload);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let timed_out = FlirError::Timeout {
+                partial_bytes: self.codec(endpoint).buffered(),
+            };
+            if remaining.is_zero() || empty_reads > ZERO_LENGTH_READ_LIMIT {
+                return Err(timed_out);
+            }
+            // Like frames: the header first, then the rest of the message it announces.
+            let codec = self.codec(endpoint);
+            let wanted = codec
+                .missing()
+                .unwrap_or_else(|| message::HEADER_LEN.saturating_sub(codec.buffered()));
+            let size = (wanted.max(1).div_ceil(packet) * packet).min(max_read);
+            let n = match self.read_bulk(endpoint, &mut buf[..size], remaining) {
+                Ok(n) => n,
+                Err(FlirError::Usb(rusb::Error::Timeout)) => return Err(timed_out),
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                empty_reads += 1;
+                thread::sleep(ZERO_LENGTH_READ_BACKOFF);
+                continue;
+            }
+            empty_reads = 0;
+            self.codec(endpoint).push(&buf[..n]);
+        }
+    }
+
+    fn codec(&mut self, endpoint: u8) -> &mut ConfigCodec {
+        if self.fileio.0.map(|e| e.address) == Some(endpoint) {
+            &mut self.fileio_codec
+        } else {
+            &mut self.config_codec
+        }
+    }
+
+    pub(crate) fn read_bulk(
+        &mut self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        self.read_bulk_with(endpoint, buf, timeout, false)
+    }
+
+    /// [`FlirOne::read_bulk`] for reads where timing out just means nothing was pending,
+    /// which the retry policy leaves alone.
+    fn poll_bulk(
+        &mut self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        self.read_bulk_with(endpoint, buf, timeout, true)
+    }
+
+    fn read_bulk_with(
+        &mut self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+        poll: bool,
+    ) -> Result<usize, FlirError> {
+        let handle = &mut self.handle;
+        let read = || handle.read_bulk(endpoint, buf, timeout);
+        let n = if poll {
+            self.retry_policy.run_polling(read)
+        } else {
+            self.retry_policy.run(read)
+        }?;
+        #[cfg(feature = "transfer-log")]
+        if let Some(log) = &mut self.transfer_log {
+            log.record(TransferKind::Bulk, endpoint, true, &buf[..n])?;
+        }
+        Ok(n)
+    }
+
+    pub(crate) fn write_bulk(
+        &mut self,
+        endpoint: u8,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        let handle = &mut self.handle;
+        let n = self
+            .retry_policy
+            .run(|| handle.write_bulk(endpoint, data, timeout))?;
+        #[cfg(feature = "transfer-log")]
+        if let Some(log) = &mut self.transfer_log {
+            log.record(TransferKind::Bulk, endpoint, false, &data[..n])?;
+        }
+        Ok(n)
+    }
+
+    /// Control transfers only start and stop protocols; everything else, every JSON
+    /// command on CONFIG and FILEIO included, goes to the bulk endpoints. A payload too
+    /// large for one control packet is sent to the CONFIG bulk endpoint instead.
+    fn send_control(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        if data.len() <= MAX_CONTROL_DATA {
+            return self.write_control(request_type, request, value, index, data, timeout);
+        }
+        let write = address(self.config.1, "config_write")?;
+        self.write_bulk(write, data, timeout)
+    }
+
+    fn write_control(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        let handle = &mut self.handle;
+        let n = self
+            .retry_policy
+            .run(|| handle.write_control(request_type, request, value, index, data, timeout))?;
+        #[cfg(feature = "transfer-log")]
+        if let Some(log) = &mut self.transfer_log {
+            let mut setup = vec![request_type, request];
+            setup.extend_from_slice(&value.to_le_bytes());
+            setup.extend_from_slice(&index.to_le_bytes());
+            setup.extend_from_slice(&(data.len() as u16).to_le_bytes());
+            setup.extend_from_slice(data);
+            log.record(TransferKind::Control, 0, false, &setup)?;
+        }
+        Ok(n)
+    }
+
+    /// Reads the next status message the camera sends on the CONFIG endpoint, starting
+    /// with the one it sends once it is up.
+    pub fn read_status(&mut self) -> Result<CameraStatus, FlirError> {
+        self.read_status_timeout(STATUS_POLL_TIMEOUT)
+    }
+
+    /// Polls [`FlirOne::read_status`] until the camera reports it is ready.
+    pub fn wait_ready(&mut self, timeout: Duration) -> Result<CameraStatus, FlirError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(FlirError::NotReady);
+            }
+            match self.read_status_timeout(remaining.min(STATUS_POLL_TIMEOUT)) {
+                Ok(status) if status.ready => return Ok(status),
+                Ok(_) | Err(FlirError::Timeout { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Battery charge and whether it is charging. Takes the latest of the battery
+    /// messages the camera sends periodically on CONFIG, reading any that are waiting;
+    /// before the first one arrives, asks with [`ConfigChannel::get_battery`].
+    pub fn battery_status(&mut self) -> Result<BatteryStatus, FlirError> {
+        for _ in 0..FLUSH_MAX_READS {
+            match self.read_status_timeout(FLUSH_TIMEOUT) {
+                Ok(_) => {}
+                Err(FlirError::Timeout { .. }) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        match self.battery {
+            Some(battery) => Ok(battery),
+            None => self.config_channel()?.get_battery(),
+        }
+    }
+
+    /// Selects the measurement range. See [`RangePreset`] for which models support what.
+    pub fn set_range(&mut self, preset: RangePreset) -> Result<(), FlirError> {
+        self.set_option("gainMode", json!(preset.as_str()))
+    }
+
+    /// The options this camera's firmware lets [`FlirOne::get_option`] read and set.
+    pub fn list_options(&mut self) -> Result<Vec<OptionDescriptor>, FlirError> {
+        self.list_options_json()?
+            .iter()
+            .map(OptionDescriptor::from_json)
+            .collect()
+    }
+
+    /// The scene presets the camera offers, for [`FlirOne::set_scene_preset`].
+    pub fn scene_presets(&mut self) -> Result<Vec<ScenePreset>, FlirError> {
+        let options = self.list_options_json()?;
+        let preset = options
+            .iter()
+            .find(|option| option.get("key").and_then(Value::as_str) == Some(SCENE_PRESET_OPTION))
+            .ok_or(FlirError::Unsupported("scene presets"))?;
+        ScenePreset::from_option_json(preset)
+    }
+
+    /// Selects one of the [`FlirOne::scene_presets`] by name.
+    pub fn set_scene_preset(&mut self, name: &str) -> Result<(), FlirError> {
+        if !self
+            .scene_presets()?
+            .iter()
+            .any(|preset| preset.name == name)
+        {
+            return Err(FlirError::Protocol(format!(
+                "no scene preset named {name:?}"
+            )));
+        }
+        self.set_option(SCENE_PRESET_OPTION, json!(name))
+    }
+
+    /// Switches the status LED, e.g. to tell apart the cameras of a rig. Firmware without
+    /// a controllable LED gives [`FlirError::Unsupported`].
+    pub fn set_led(&mut self, on: bool) -> Result<(), FlirError> {
+        if !self
+            .list_options()?
+            .iter()
+            .any(|option| option.key == LED_OPTION)
+        {
+            return Err(FlirError::Unsupported("LED control"));
+        }
+        self.set_option(LED_OPTION, json!(on))
+    }
+
+    /// Which way up the camera is held, from the latest frame's status or else the
+    /// camera's orientation option. Combine with [`ThermalImage::rotate`] and
+    /// [`Orientation::upright_rotation`] to keep images upright. Firmware that reports
+    /// neither gives [`FlirError::Unsupported`].
+    pub fn orientation(&mut self) -> Result<Orientation, FlirError> {
+        if let Some(orientation) = self.last_frame.as_ref().and_then(Frame::orientation) {
+            return Ok(orientation);
+        }
+        if !self
+            .list_options()?
+            .iter()
+            .any(|option| option.key == ORIENTATION_OPTION)
+        {
+            return Err(FlirError::Unsupported("orientation"));
+        }
+        let value = self.get_option(ORIENTATION_OPTION)?;
+        value
+            .as_f64()
+            .and_then(Orientation::from_degrees)
+            .ok_or_else(|| FlirError::Protocol(format!("orientation is not an angle: {value}")))
+    }
+
+    /// The frame rate the camera streams at, in frames per second. Fixed-rate firmware
+    /// gives [`FlirError::Unsupported`].
+    pub fn refresh_rate(&mut self) -> Result<f32, FlirError> {
+        self.frame_rate_option()?;
+        let value = self.get_option(FRAME_RATE_OPTION)?;
+        value
+            .as_f64()
+            .map(|rate| rate as f32)
+            .ok_or_else(|| FlirError::Protocol(format!("frame rate is not a number: {value}")))
+    }
+
+    /// Asks for `fps` frames per second, e.g. a lower rate to save power. Rates outside
+    /// the range the firmware gives are refused.
+    pub fn set_refresh_rate(&mut self, fps: f32) -> Result<(), FlirError> {
+        let option = self.frame_rate_option()?;
+        if let Some((min, max)) = option.range {
+            if !(min..=max).contains(&f64::from(fps)) {
+                return Err(FlirError::Protocol(format!(
+                    "frame rate {fps} is outside {min}..={max}"
+                )));
+            }
+        }
+        self.set_option(FRAME_RATE_OPTION, json!(fps))
+    }
+
+    fn frame_rate_option(&mut self) -> Result<OptionDescriptor, FlirError> {
+        self.list_options()?
+            .into_iter()
+            .find(|option| option.key == FRAME_RATE_OPTION)
+            .ok_or(FlirError::Unsupported("refresh rate control"))
+    }
+
+    /// The lens field of view as the camera reports it, or else the datasheet value for
+    /// its model, see [`Fov::for_product`]. With [`Fov::pixel_size`] and the distance to
+    /// the target this gives the size of what each pixel sees.
+    pub fn field_of_view(&mut self) -> Result<Fov, FlirError> {
+        if self
+            .list_options()?
+            .iter()
+            .any(|option| option.key == FIELD_OF_VIEW_OPTION)
+        {
+            let value = self.get_option(FIELD_OF_VIEW_OPTION)?;
+            return serde_json::from_value(value.clone()).map_err(|_| {
+                FlirError::Protocol(format!("field of view is not horizontal/vertical: {value}"))
+            });
+        }
+        let strings = self.handle.string_descriptors()?;
+        Ok(Fov::for_product(strings.product.as_deref()))
+    }
+
+    fn list_options_json(&mut self) -> Result<Vec<Value>, FlirError> {
+        let read = address(self.config.0, "config_read")?;
+        let write = address(self.config.1, "config_write")?;
+        let (data, _) = self.request(read, write, "listOptions", json!({}), CONFIG_TIMEOUT)?;
+        match data.get("options") {
+            Some(Value::Array(options)) => Ok(options.clone()),
+            _ => Err(FlirError::Protocol(format!(
+                "listOptions reply has no options: {data}"
+            ))),
+        }
+    }
+
+    /// Reads a camera option, the counterpart of the options [`FlirOne::set_range`] and
+    /// friends set.
+    pub fn get_option(&mut self, option: &str) -> Result<Value, FlirError> {
+        let read = address(self.config.0, "config_read")?;
+        let write = address(self.config.1, "config_write")?;
+        let (data, _) = self.request(
+            read,
+            write,
+            "getOption",
+            json!({ "option": option }),
+            CONFIG_TIMEOUT,
+        )?;
+        Ok(data.get("value").cloned().unwrap_or(data))
+    }
+
+    /// The firmware version exactly as the camera reports it, for vendor formats
+    /// [`FlirOne::firmware_version`] cannot parse.
+    pub fn firmware_version_string(&mut self) -> Result<String, FlirError> {
+        match self.get_option(FIRMWARE_VERSION_OPTION)? {
+            Value::String(version) => Ok(version),
+            other => Err(FlirError::Protocol(format!(
+                "firmware version is not a string: {other}"
+            ))),
+        }
+    }
+
+    /// The firmware version, for branching on behaviour that differs between revisions.
+    pub fn firmware_version(&mut self) -> Result<semver::Version, FlirError> {
+        parse_firmware_version(&self.firmware_version_string()?)
+    }
+
+    /// A string option, or `None` if the camera does not report it.
+    fn string_option(&mut self, option: &str) -> Option<String> {
+        match self.get_option(option).ok()? {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn set_option(&mut self, option: &str, value: Value) -> Result<(), FlirError> {
+        let read = address(self.config.0, "config_read")?;
+        let write = address(self.config.1, "config_write")?;
+        self.request(
+            read,
+            write,
+            "setOption",
+            json!({ "option": option, "value": value }),
+            CONFIG_TIMEOUT,
+        )?;
+        Ok(())
+    }
+
+    fn read_status_timeout(&mut self, timeout: Duration) -> Result<CameraStatus, FlirError> {
+        let read = address(self.config.0, "config_read")?;
+        let payload = self.read_message(read, timeout)?;
+        let (json, _) = message::split_payload(&payload)?;
+        let status = CameraStatus::from_json(json);
+        self.observe(&status);
+        Ok(status)
+    }
+
+    /// Keeps track of what the camera reports unprompted.
+    fn observe(&mut self, status: &CameraStatus) {
+        self.battery = BatteryStatus::update(self.battery, status);
+    }
+
+    /// Names of the images saved on the camera, e.g. with the physical shutter button.
+    pub fn list_stored_images(&mut self) -> Result<Vec<String>, FlirError> {
+        let files = self.file_system()?.list_dir(STORED_IMAGES_DIR)?;
+        Ok(files
+            .into_iter()
+            .filter(|name| name.to_ascii_lowercase().ends_with(".jpg"))
+            .collect())
+    }
+
+    /// Downloads a stored image as the radiometric JPEG the camera wrote: a regular JPEG
+    /// carrying the raw thermal plane and calibration in its APP1 "FLIR" segments.
+    pub fn download_stored_image(&mut self, name: &str) -> Result<Vec<u8>, FlirError> {
+        let path = format!("{STORED_IMAGES_DIR}/{name}");
+        let bytes = self.file_system()?.read_file(&path)?;
+        if !bytes.starts_with(&JPEG_SOI) {
+            return Err(FlirError::Protocol(format!("{path} is not a JPEG")));
+        }
+        Ok(bytes)
+    }
+
+    /// Reads the unit's calibration from the newest image stored on the camera, which
+    /// carries it in its FFF metadata.
+    pub fn read_calibration(&mut self) -> Result<Calibration, FlirError> {
+        let mut images = self.list_stored_images()?;
+        images.sort();
+        let Some(newest) = images.last() else {
+            return Err(FlirError::NoCalibration(
+                "no stored image to read it from".to_owned(),
+            ));
+        };
+        let jpeg = self.download_stored_image(newest)?;
+        // Firmware that stores images without the metadata has no calibration to give.
+        fff::parse_calibration(&jpeg).map_err(|e| match e {
+            FlirError::InvalidFormat(msg) => FlirError::NoCalibration(format!("{newest}: {msg}")),
+            e => e,
+        })
+    }
+
+    /// [`FlirOne::read_calibration`], cached in `cache_dir` as `<serial>.json` so later
+    /// runs skip the download. Cameras without a serial number are never cached.
+    pub fn calibration_cached(&mut self, cache_dir: &Path) -> Result<Calibration, FlirError> {
+        let Some(serial) = self.handle.string_descriptors()?.serial_number else {
+            return self.read_calibration();
+        };
+        let path = cache_dir.join(format!("{serial}.json"));
+        if path.exists() {
+            return Calibration::load(&path);
+        }
+        let calibration = self.read_calibration()?;
+        fs::create_dir_all(cache_dir)?;
+        calibration.save(&path)?;
+        Ok(calibration)
+    }
+
+    /// Typed requests on the CONFIG endpoint, instead of crafting the JSON by hand.
+    pub fn config_channel(&mut self) -> Result<ConfigChannel<'_, T>, FlirError> {
+        Ok(ConfigChannel {
+            read: address(self.config.0, "config_read")?,
+            write: address(self.config.1, "config_write")?,
+            device: self,
+        })
+    }
+
+    /// Files on the camera, over FILEIO. Needs [`FlirOne::connect`] first.
+    pub fn file_system(&mut self) -> Result<FileSystem<'_, T>, FlirError> {
+        if !self.connected || !self.expect_file_data {
+            return Err(FlirError::NotConnected);
+        }
+        Ok(FileSystem {
+            read: address(self.fileio.0, "fileio_read")?,
+            write: address(self.fileio.1, "fileio_write")?,
+            device: self,
+        })
+    }
+}
+
+/// Address of an endpoint the builder may have left unset, for methods that need it.
+fn address(endpoint: Option<EndpointInfo>, name: &'static str) -> Result<u8, FlirError> {
+    endpoint
+        .map(|endpoint| endpoint.address)
+        .ok_or(FlirError::EndpointMissing(name))
+}
+
+/// `for frame in &mut flir` is [`FlirOne::frames`], except that the camera is connected
+/// and the frame protocol started on the first iteration if they are not already.
+impl<'a, T: Transport> IntoIterator for &'a mut FlirOne<T> {
+    type Item = Result<Frame, FlirError>;
+    type IntoIter = FrameStream<'a, T>;
+
+    fn into_iter(self) -> FrameStream<'a, T> {
+        FrameStream::new(self).start_lazily()
+    }
+}
+
+/// Stops the protocols that were started, so the camera is left idle for the next user,
+/// releases the interfaces the builder claimed and reattaches the kernel drivers detached
+/// to claim them. Errors are ignored; the device may already be gone.
+impl<T: Transport> Drop for FlirOne<T> {
+    fn drop(&mut self) {
+        if self.expect_frame_data {
+            let _ = self.toggle_communication(ProtocolType::FRAME, false);
+        }
+        if self.expect_file_data {
+            let _ = self.toggle_communication(ProtocolType::FILEIO, false);
+        }
+        if self.config_started {
+            let _ = self.toggle_communication(ProtocolType::CONFIG, false);
+        }
+        for interface in self.interfaces.drain(..) {
+            let _ = self.handle.release_interface(interface);
+        }
+        for interface in self.detached.drain(..) {
+            let _ = self.handle.attach_kernel_driver(interface);
+        }
+    }
+}
+
+/// Parses `raw` as semver, also accepting a leading `v` and versions with only one or two
+/// components, which are padded with zeros.
+fn parse_firmware_version(raw: &str) -> Result<semver::Version, FlirError> {
+    let version = raw.trim();
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    if let Ok(version) = semver::Version::parse(version) {
+        return Ok(version);
+    }
+    let parts = version
+        .split('.')
+        .map(str::parse)
+        .collect::<Result<Vec<u64>, _>>()
+        .map_err(|_| FlirError::InvalidFormat(format!("unparseable firmware version {raw:?}")))?;
+    match parts[..] {
+        [major] => Ok(semver::Version::new(major, 0, 0)),
+        [major, minor] => Ok(semver::Version::new(major, minor, 0)),
+        _ => Err(FlirError::InvalidFormat(format!(
+            "unparseable firmware version {raw:?}"
+        ))),
+    }
+}
+
+pub struct FlirOneBuilder<T: Transport = DeviceHandle<GlobalContext>> {
+    config_read: Option<EndpointInfo>,
+    config_write: Option<EndpointInfo>,
+    frame_read: Option<EndpointInfo>,
+    frame_write: Option<EndpointInfo>,
+    fileio_read: Option<EndpointInfo>,
+    fileio_write: Option<EndpointInfo>,
+    allow_any_device: bool,
+    usb_log_level: Option<LogLevel>,
+    claim_only: Option<Vec<u8>>,
+    configuration: Option<u8>,
+    retry_policy: RetryPolicy,
+    config_read_size: usize,
+    max_frame_read: usize,
+    calibration: Option<Calibration>,
+    interfaces: Vec<u8>,
+    detached: Vec<u8>,
+
+    handle: T,
+}
+
+impl<T: Transport + Send + 'static> FlirOne<T> {
+    /// Moves the device to a background thread that reads frames into a queue of up to
+    /// `capacity` frames, handled according to `policy` when it fills up. With the
+    /// `stream` feature the result is also a `futures_core::Stream` of frames.
+    pub fn spawn_capture(self, capacity: usize, policy: QueuePolicy) -> ThreadedCapture<T> {
+        ThreadedCapture::spawn(self, capacity, policy)
+    }
+}
+
+impl<T: Transport> FlirOneBuilder<T> {
+    pub fn new(handle: T) -> Self {
+        FlirOneBuilder {
+            config_read: None,
+            config_write: None,
+            frame_read: None,
+            frame_write: None,
+            fileio_read: None,
+            fileio_write: None,
+            allow_any_device: false,
+            usb_log_level: None,
+            claim_only: None,
+            configuration: None,
+            retry_policy: RetryPolicy::NONE,
+            config_read_size: CONFIG_READ_SIZE,
+            max_frame_read: MAX_FRAME_SIZE,
+            calibration: None,
+            interfaces: Vec::new(),
+            detached: Vec::new(),
+            handle,
+        }
+    }
+
+    pub fn handle(&self) -> &T {
+        &self.handle
+    }
+
+    pub fn handle_mut(&mut self) -> &mut T {
+        &mut self.handle
+    }
+
+    /// Restricts [`FlirOneBuilder::claim`] to these interface numbers, leaving every other
+    /// interface to whichever driver already has it.
+    pub fn claim_only(mut self, interfaces: &[u8]) -> Self {
+        self.claim_only = Some(interfaces.to_vec());
+        self
+    }
+
+    /// Switches the device to configuration `number` before
+    /// [`FlirOneBuilder::claim_and_configure`] claims its interfaces. Without it the
+    /// active configuration is used as is.
+    pub fn use_configuration(mut self, number: u8) -> Self {
+        self.configuration = Some(number);
+        self
+    }
+
+    /// Claims `interface` unless [`FlirOneBuilder::claim_only`] excludes it. Returns whether
+    /// the interface was claimed; endpoints of unclaimed interfaces should not be used.
+    pub fn claim(&mut self, interface: u8) -> Result<bool, FlirError> {
+        if let Some(only) = &self.claim_only {
+            if !only.contains(&interface) {
+                return Ok(false);
+            }
+        }
+        self.handle.claim_interface(interface)?;
+        if !self.interfaces.contains(&interface) {
+            self.interfaces.push(interface);
+        }
+        Ok(true)
+    }
+
+    /// Uses the endpoints found by [`FlirOne::claim_and_configure`]. Its interfaces are
+    /// released on drop, and any kernel drivers it detached are reattached.
+    pub fn claimed(mut self, claimed: &ClaimedInterfaces) -> Self {
+        self.apply_claimed(claimed);
+        self
+    }
+
+    fn apply_claimed(&mut self, claimed: &ClaimedInterfaces) {
+        for &interface in &claimed.interfaces {
+            if !self.interfaces.contains(&interface) {
+                self.interfaces.push(interface);
+            }
+        }
+        for &interface in &claimed.detached {
+            if !self.detached.contains(&interface) {
+                self.detached.push(interface);
+            }
+        }
+        self.config_read = claimed.config_read.or(self.config_read);
+        self.config_write = claimed.config_write.or(self.config_write);
+        self.fileio_read = claimed.fileio_read.or(self.fileio_read);
+        self.fileio_write = claimed.fileio_write.or(self.fileio_write);
+        self.frame_read = claimed.frame_read.or(self.frame_read);
+        self.frame_write = claimed.frame_write.or(self.frame_write);
+    }
+
+    pub fn config_read(mut self, endpoint: impl Into<EndpointInfo>) -> Self {
+        self.config_read = Some(endpoint.into());
+        self
+    }
+
+    pub fn config_write(mut self, endpoint: impl Into<EndpointInfo>) -> Self {
+        self.config_write = Some(endpoint.into());
+        self
+    }
+
+    pub fn frame_read(mut self, endpoint: impl Into<EndpointInfo>) -> Self {
+        self.frame_read = Some(endpoint.into());
+        self
+    }
+
+    pub fn frame_write(mut self, endpoint: impl Into<EndpointInfo>) -> Self {
+        self.frame_write = Some(endpoint.into());
+        self
+    }
+
+    pub fn fileio_read(mut self, endpoint: impl Into<EndpointInfo>) -> Self {
+        self.fileio_read = Some(endpoint.into());
+        self
+    }
+
+    pub fn fileio_write(mut self, endpoint: impl Into<EndpointInfo>) -> Self {
+        self.fileio_write = Some(endpoint.into());
+        self
+    }
+
+    /// Compares the endpoints set so far with the addresses in [`endpoints`] and returns
+    /// each one that differs, for the caller to report. Firmware that renumbers its
+    /// endpoints shows up here rather than as a camera that never answers.
+    pub fn verify_addresses(&self) -> Vec<AddressMismatch> {
+        let expected = [
+            ("config_read", self.config_read, endpoints::CONFIG_READ),
+            ("config_write", self.config_write, endpoints::CONFIG_WRITE),
+            ("fileio_read", self.fileio_read, endpoints::FILEIO_READ),
+            ("fileio_write", self.fileio_write, endpoints::FILEIO_WRITE),
+            ("frame_read", self.frame_read, endpoints::FRAME_READ),
+            ("frame_write", self.frame_write, endpoints::FRAME_WRITE),
+        ];
+        expected
+            .into_iter()
+            .filter_map(|(endpoint, info, expected)| {
+                let found = info?.address;
+                (found != expected).then_some(AddressMismatch {
+                    endpoint,
+                    expected,
+                    found,
+                })
+            })
+            .collect()
+    }
+
+    /// Retries transfers that fail with transient USB errors. By default nothing is retried.
+    /// Timeouts apply to each attempt; see [`RetryPolicy`] for the reads that never retry
+    /// them.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Largest single read on the CONFIG and FILEIO endpoints, [`CONFIG_READ_SIZE`] by
+    /// default. Reads are sized to what the message header announces, rounded up to
+    /// whole packets, so this only bounds the buffer.
+    pub fn config_read_size(mut self, bytes: usize) -> Self {
+        self.config_read_size = bytes;
+        self
+    }
+
+    /// Largest single read on the FRAME endpoint, [`MAX_FRAME_SIZE`] by default. Frames
+    /// larger than this arrive over several reads.
+    pub fn max_frame_read(mut self, bytes: usize) -> Self {
+        self.max_frame_read = bytes;
+        self
+    }
+
+    /// Per-unit constants for the device's temperature conveniences, such as
+    /// [`FlirOne::last_frame_stats`], so they need not be passed to every call.
+    pub fn calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    /// Skips the vendor/product ID check in [`FlirOneBuilder::build`].
+    pub fn allow_any_device(mut self) -> Self {
+        self.allow_any_device = true;
+        self
+    }
+
+    /// Sets the libusb log level of the handle's context when the device is built.
+    /// With the global context this still applies process-wide, so it is left untouched
+    /// unless asked for.
+    pub fn usb_log_level(mut self, level: LogLevel) -> Self {
+        self.usb_log_level = Some(level);
+        self
+    }
+
+    /// Endpoints may be left unset for firmware that lacks them; methods that need one
+    /// then fail with [`FlirError::EndpointMissing`].
+    pub fn build(mut self) -> Result<FlirOne<T>, FlirError> {
+        if let Some(level) = self.usb_log_level {
+            self.handle.set_log_level(level);
+        }
+
+        if !self.allow_any_device {
+            let (vendor_id, product_id) = self.handle.device_ids()?;
+            if vendor_id != FLIR_VENDOR_ID || !FLIR_ONE_PRODUCT_IDS.contains(&product_id) {
+                return Err(FlirError::UnexpectedDevice {
+                    vendor_id,
+                    product_id,
+                });
+            }
+        }
+
+        Ok(FlirOne {
+            handle: self.handle,
+            config: (self.config_read, self.config_write),
+            frame: (self.frame_read, self.frame_write),
+            fileio: (self.fileio_read, self.fileio_write),
+            connected: false,
+            config_started: false,
+            expect_file_data: false,
+            expect_frame_data: false,
+            paused: false,
+            power_save: false,
+            frame_parser: FrameParser::new(),
+            config_codec: ConfigCodec::new(),
+            fileio_codec: ConfigCodec::new(),
+            config_read_size: self.config_read_size,
+            max_frame_read: self.max_frame_read,
+            calibration: self.calibration,
+            last_frame: None,
+            battery: None,
+            interfaces: self.interfaces,
+            detached: self.detached,
+            retry_policy: self.retry_policy,
+            #[cfg(feature = "transfer-log")]
+            transfer_log: None,
+        })
+    }
+}
+
+impl<C: UsbContext> FlirOneBuilder<DeviceHandle<C>> {
+    /// Like [`FlirOne::claim_and_configure`], but honours [`FlirOneBuilder::claim_only`]
+    /// and [`FlirOneBuilder::use_configuration`] and applies the endpoints it finds to the
+    /// builder.
+    pub fn claim_and_configure(&mut self) -> Result<ClaimedInterfaces, FlirError> {
+        if let Some(number) = self.configuration {
+            if self.handle.active_configuration()? != number {
+                self.handle.set_active_configuration(number)?;
+            }
+        }
+        let claimed = ClaimedInterfaces::claim(&mut self.handle, self.claim_only.as_deref())?;
+        self.apply_claimed(&claimed);
+        Ok(claimed)
+    }
+
+    /// [`FlirOneBuilder::claim_and_configure`] for cameras whose endpoint addresses differ
+    /// from the usual ones: the protocols are assigned by transfer type, direction and
+    /// interface class instead, see [`FlirError::EndpointLayout`] for when that fails.
+    pub fn auto_detect(&mut self) -> Result<ClaimedInterfaces, FlirError> {
+        if let Some(number) = self.configuration {
+            if self.handle.active_configuration()? != number {
+                self.handle.set_active_configuration(number)?;
+            }
+        }
+        let claimed = ClaimedInterfaces::claim_auto(&mut self.handle, self.claim_only.as_deref())?;
+        self.apply_claimed(&claimed);
+        Ok(claimed)
+    }
+}
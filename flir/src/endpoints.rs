@@ -0,0 +1,31 @@
+//! Endpoint addresses of the FLIR One's three bulk protocols. Each protocol has an IN
+//! endpoint the camera sends on and an OUT endpoint it receives on, numbered in pairs:
+//! 1/2 for CONFIG, 3/4 for FILEIO and 5/6 for FRAME.
+
+use std::fmt;
+
+pub const CONFIG_READ: u8 = 0x81;
+pub const CONFIG_WRITE: u8 = 0x02;
+pub const FILEIO_READ: u8 = 0x83;
+pub const FILEIO_WRITE: u8 = 0x04;
+pub const FRAME_READ: u8 = 0x85;
+pub const FRAME_WRITE: u8 = 0x06;
+
+/// An endpoint whose address is not the one the protocol expects, as reported by
+/// [`FlirOneBuilder::verify_addresses`](crate::FlirOneBuilder::verify_addresses).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressMismatch {
+    pub endpoint: &'static str,
+    pub expected: u8,
+    pub found: u8,
+}
+
+impl fmt::Display for AddressMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} endpoint is {:#04x}, expected {:#04x}",
+            self.endpoint, self.found, self.expected
+        )
+    }
+}
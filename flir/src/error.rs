@@ -0,0 +1,140 @@
+use std::{error::Error, fmt};
+
+use crate::thermal::Rect;
+
+#[derive(Debug)]
+pub enum FlirError {
+    Usb(rusb::Error),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Image(image::ImageError),
+    Protocol(String),
+    InvalidFormat(String),
+    /// No FLIR One is plugged in.
+    DeviceNotFound,
+    NotConnected,
+    /// The FRAME protocol was started before CONFIG, which the camera needs first.
+    ConfigNotStarted,
+    NotReady,
+    /// Reads keep completing without data.
+    NoData,
+    /// A [`CancelToken`](crate::CancelToken) was cancelled during the read.
+    Cancelled,
+    /// No calibration could be found for the camera. Temperatures are not guessed; use
+    /// [`Calibration::default_flir_one`](crate::Calibration::default_flir_one) to opt into
+    /// typical constants.
+    NoCalibration(String),
+    /// The camera's firmware does not offer the named feature.
+    Unsupported(&'static str),
+    /// No complete frame or CONFIG/FILEIO message arrived in time.
+    Timeout {
+        partial_bytes: usize,
+    },
+    EndpointMissing(&'static str),
+    /// The device's interfaces do not look like a FLIR One's.
+    EndpointLayout(String),
+    UnexpectedDevice {
+        vendor_id: u16,
+        product_id: u16,
+    },
+    DimensionMismatch {
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    /// A buffer had the wrong number of elements: pixels, emissivities or frame bytes.
+    BufferSize {
+        expected: usize,
+        found: usize,
+    },
+    OutOfBounds {
+        rect: Rect,
+        bounds: (u32, u32),
+    },
+}
+
+impl fmt::Display for FlirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlirError::Usb(e) => write!(f, "usb error: {e}"),
+            FlirError::Io(e) => write!(f, "i/o error: {e}"),
+            FlirError::Json(e) => write!(f, "invalid json: {e}"),
+            FlirError::Image(e) => write!(f, "image error: {e}"),
+            FlirError::Protocol(msg) => write!(f, "protocol error: {msg}"),
+            FlirError::InvalidFormat(msg) => write!(f, "invalid format: {msg}"),
+            FlirError::DeviceNotFound => write!(f, "no FLIR One found"),
+            FlirError::NotConnected => write!(f, "camera is not connected"),
+            FlirError::ConfigNotStarted => {
+                write!(f, "the CONFIG protocol must be started before FRAME")
+            }
+            FlirError::NotReady => write!(f, "camera did not report ready in time"),
+            FlirError::NoData => write!(f, "camera keeps sending empty transfers"),
+            FlirError::Cancelled => write!(f, "read was cancelled"),
+            FlirError::NoCalibration(reason) => write!(f, "no calibration available: {reason}"),
+            FlirError::Unsupported(feature) => write!(f, "camera does not support {feature}"),
+            FlirError::Timeout { partial_bytes } => {
+                write!(
+                    f,
+                    "timed out with {partial_bytes} bytes of a frame or message received"
+                )
+            }
+            FlirError::EndpointMissing(name) => write!(f, "{name} not set"),
+            FlirError::EndpointLayout(msg) => write!(f, "unexpected endpoint layout: {msg}"),
+            FlirError::UnexpectedDevice {
+                vendor_id,
+                product_id,
+            } => write!(
+                f,
+                "{vendor_id:04x}:{product_id:04x} is not a known FLIR One device"
+            ),
+            FlirError::DimensionMismatch { expected, found } => write!(
+                f,
+                "image dimensions differ: expected {}x{}, found {}x{}",
+                expected.0, expected.1, found.0, found.1
+            ),
+            FlirError::BufferSize { expected, found } => {
+                write!(f, "expected {expected} elements, found {found}")
+            }
+            FlirError::OutOfBounds { rect, bounds } => write!(
+                f,
+                "{}x{} region at ({}, {}) does not fit in a {}x{} image",
+                rect.width, rect.height, rect.x, rect.y, bounds.0, bounds.1
+            ),
+        }
+    }
+}
+
+impl Error for FlirError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FlirError::Usb(e) => Some(e),
+            FlirError::Io(e) => Some(e),
+            FlirError::Json(e) => Some(e),
+            FlirError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusb::Error> for FlirError {
+    fn from(e: rusb::Error) -> Self {
+        FlirError::Usb(e)
+    }
+}
+
+impl From<std::io::Error> for FlirError {
+    fn from(e: std::io::Error) -> Self {
+        FlirError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for FlirError {
+    fn from(e: image::ImageError) -> Self {
+        FlirError::Image(e)
+    }
+}
+
+impl From<serde_json::Error> for FlirError {
+    fn from(e: serde_json::Error) -> Self {
+        FlirError::Json(e)
+    }
+}
@@ -0,0 +1,261 @@
+//! FLIR radiometric JPEGs: an ordinary JPEG whose APP1 "FLIR" segments, concatenated,
+//! form an FFF file holding the raw thermal plane and the camera calibration.
+
+use crate::{
+    radiometry::{AtmosphericConstants, Calibration, ObjectParameters},
+    FlirError, PaletteLut, ThermalImage,
+};
+
+const APP1: u8 = 0xe1;
+const SOS: u8 = 0xda;
+const FLIR_SEGMENT: &[u8] = b"FLIR\0";
+const FLIR_SEGMENT_HEADER_LEN: usize = 8;
+
+const FFF_MAGIC: &[u8] = b"FFF\0";
+const FFF_HEADER_LEN: usize = 64;
+const DIRECTORY_ENTRY_LEN: usize = 32;
+
+const RECORD_RAW_DATA: u16 = 0x0001;
+const RECORD_CAMERA_INFO: u16 = 0x0020;
+const RECORD_PALETTE_INFO: u16 = 0x0022;
+
+const RAW_DATA_PIXELS: usize = 32;
+const PNG_MAGIC: &[u8] = b"\x89PNG";
+const KELVIN_OFFSET: f32 = 273.15;
+
+const PALETTE_NAME: usize = 0x50;
+const PALETTE_NAME_LEN: usize = 32;
+const PALETTE_COLORS: usize = 0x70;
+
+pub fn parse_radiometric_jpeg(bytes: &[u8]) -> Result<ThermalImage, FlirError> {
+    let fff = extract_fff(bytes)?;
+    let raw = find_record(&fff, RECORD_RAW_DATA)?;
+    parse_raw_data(raw)
+}
+
+pub fn parse_calibration(bytes: &[u8]) -> Result<Calibration, FlirError> {
+    let fff = extract_fff(bytes)?;
+    let info = find_record(&fff, RECORD_CAMERA_INFO)?;
+    parse_camera_info(info)
+}
+
+/// The palette the camera rendered the JPEG with, such as FLIR's Iron, as stored in the
+/// file's PaletteInfo record.
+pub fn parse_palette(bytes: &[u8]) -> Result<PaletteLut, FlirError> {
+    let fff = extract_fff(bytes)?;
+    let info = find_record(&fff, RECORD_PALETTE_INFO)?;
+    parse_palette_info(info)
+}
+
+fn invalid(msg: impl Into<String>) -> FlirError {
+    FlirError::InvalidFormat(msg.into())
+}
+
+fn extract_fff(jpeg: &[u8]) -> Result<Vec<u8>, FlirError> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return Err(invalid("not a JPEG"));
+    }
+
+    let mut segments = Vec::new();
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() {
+        if jpeg[pos] != 0xff {
+            return Err(invalid(format!("expected JPEG marker at offset {pos}")));
+        }
+        let marker = jpeg[pos + 1];
+        if marker == SOS {
+            break;
+        }
+        if marker == 0x01 || (0xd0..=0xd7).contains(&marker) || marker == 0xff {
+            pos += if marker == 0xff { 1 } else { 2 };
+            continue;
+        }
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        let data = jpeg
+            .get(pos + 4..pos + 2 + len)
+            .ok_or_else(|| invalid("truncated JPEG segment"))?;
+        if marker == APP1 && data.starts_with(FLIR_SEGMENT) && data.len() >= 8 {
+            segments.push((data[6], &data[FLIR_SEGMENT_HEADER_LEN..]));
+        }
+        pos += 2 + len;
+    }
+
+    if segments.is_empty() {
+        return Err(invalid("no FLIR APP1 segments"));
+    }
+    segments.sort_by_key(|&(index, _)| index);
+    Ok(segments
+        .into_iter()
+        .flat_map(|(_, data)| data)
+        .copied()
+        .collect())
+}
+
+fn find_record(fff: &[u8], kind: u16) -> Result<&[u8], FlirError> {
+    if fff.len() < FFF_HEADER_LEN || !fff.starts_with(FFF_MAGIC) {
+        return Err(invalid("missing FFF header"));
+    }
+    // The version is 100-199; anything else means the header is little-endian.
+    let big_endian = (100..200).contains(&u32::from_be_bytes(fff[20..24].try_into().unwrap()));
+    let read_u32 = |at: usize| -> u32 {
+        let b = fff[at..at + 4].try_into().unwrap();
+        if big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        }
+    };
+    let read_u16 = |at: usize| -> u16 {
+        let b = fff[at..at + 2].try_into().unwrap();
+        if big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        }
+    };
+
+    let directory = read_u32(24) as usize;
+    let count = read_u32(28) as usize;
+    for i in 0..count {
+        let entry = directory + i * DIRECTORY_ENTRY_LEN;
+        if entry + DIRECTORY_ENTRY_LEN > fff.len() {
+            return Err(invalid("truncated FFF record directory"));
+        }
+        if read_u16(entry) != kind {
+            continue;
+        }
+        let offset = read_u32(entry + 12) as usize;
+        let len = read_u32(entry + 16) as usize;
+        return fff
+            .get(offset..offset + len)
+            .ok_or_else(|| invalid(format!("FFF record {kind:#06x} out of bounds")));
+    }
+    Err(invalid(format!("no FFF record of type {kind:#06x}")))
+}
+
+/// Records start with the value 2 written in their own byte order.
+fn record_is_little_endian(record: &[u8]) -> bool {
+    record.len() >= 2 && u16::from_le_bytes([record[0], record[1]]) == 2
+}
+
+fn parse_raw_data(record: &[u8]) -> Result<ThermalImage, FlirError> {
+    if record.len() < RAW_DATA_PIXELS {
+        return Err(invalid("truncated RawData record"));
+    }
+    let le = record_is_little_endian(record);
+    let u16_at = |at: usize| {
+        let b = [record[at], record[at + 1]];
+        if le {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        }
+    };
+    let width = u16_at(2) as u32;
+    let height = u16_at(4) as u32;
+    let data = &record[RAW_DATA_PIXELS..];
+
+    let pixels = if data.starts_with(PNG_MAGIC) {
+        let png = image::load_from_memory_with_format(data, image::ImageFormat::Png)
+            .map_err(|e| invalid(format!("embedded PNG: {e}")))?
+            .into_luma16();
+        if png.dimensions() != (width, height) {
+            return Err(FlirError::DimensionMismatch {
+                expected: (width, height),
+                found: png.dimensions(),
+            });
+        }
+        // FLIR writes little-endian samples into a PNG, which is big-endian by definition.
+        png.into_raw().into_iter().map(u16::swap_bytes).collect()
+    } else {
+        data.chunks_exact(2)
+            .take(width as usize * height as usize)
+            .map(|b| {
+                if le {
+                    u16::from_le_bytes([b[0], b[1]])
+                } else {
+                    u16::from_be_bytes([b[0], b[1]])
+                }
+            })
+            .collect()
+    };
+
+    ThermalImage::new(width, height, pixels)
+}
+
+fn parse_camera_info(record: &[u8]) -> Result<Calibration, FlirError> {
+    if record.len() < 0x310 {
+        return Err(invalid("truncated CameraInfo record"));
+    }
+    let le = record_is_little_endian(record);
+    let bytes_at = |at: usize| -> [u8; 4] {
+        let b: [u8; 4] = record[at..at + 4].try_into().unwrap();
+        if le {
+            b
+        } else {
+            [b[3], b[2], b[1], b[0]]
+        }
+    };
+    let f32_at = |at: usize| f32::from_le_bytes(bytes_at(at));
+    let celsius_at = |at: usize| f32_at(at) - KELVIN_OFFSET;
+
+    let humidity = f32_at(0x3c);
+    Ok(Calibration {
+        planck_r1: f32_at(0x58),
+        planck_r2: f32_at(0x30c),
+        planck_b: f32_at(0x5c),
+        planck_f: f32_at(0x60),
+        planck_o: i32::from_le_bytes(bytes_at(0x308)) as f32,
+        atmosphere: AtmosphericConstants {
+            alpha1: f32_at(0x70),
+            alpha2: f32_at(0x74),
+            beta1: f32_at(0x78),
+            beta2: f32_at(0x7c),
+            x: f32_at(0x80),
+        },
+        object: ObjectParameters {
+            emissivity: f32_at(0x20),
+            distance: f32_at(0x24),
+            reflected_temperature: celsius_at(0x28),
+            atmospheric_temperature: celsius_at(0x2c),
+            ir_window_temperature: celsius_at(0x30),
+            ir_window_transmission: f32_at(0x34),
+            // Some firmware stores a percentage rather than a fraction.
+            relative_humidity: if humidity > 2.0 {
+                humidity / 100.0
+            } else {
+                humidity
+            },
+        },
+    })
+}
+
+/// The color count is in the first byte; colors are stored as Y, Cr, Cb triplets.
+fn parse_palette_info(record: &[u8]) -> Result<PaletteLut, FlirError> {
+    let count = *record
+        .first()
+        .ok_or_else(|| invalid("empty PaletteInfo record"))? as usize;
+    let colors = record
+        .get(PALETTE_COLORS..PALETTE_COLORS + 3 * count)
+        .ok_or_else(|| invalid("truncated PaletteInfo record"))?;
+    let name = &record[PALETTE_NAME..PALETTE_NAME + PALETTE_NAME_LEN];
+    let name = String::from_utf8_lossy(name.split(|&b| b == 0).next().unwrap_or_default());
+    PaletteLut::new(
+        name.trim(),
+        colors
+            .chunks_exact(3)
+            .map(|c| ycrcb_to_rgb(c[0], c[1], c[2]))
+            .collect(),
+    )
+}
+
+/// Full-range (JPEG) YCbCr, as FLIR uses.
+fn ycrcb_to_rgb(y: u8, cr: u8, cb: u8) -> [u8; 3] {
+    let (y, cr, cb) = (y as f32, cr as f32 - 128.0, cb as f32 - 128.0);
+    [
+        y + 1.402 * cr,
+        y - 0.344_136 * cb - 0.714_136 * cr,
+        y + 1.772 * cb,
+    ]
+    .map(|v| v.round().clamp(0.0, 255.0) as u8)
+}
@@ -0,0 +1,309 @@
+//! FILEIO protocol: access to files on the camera's internal storage.
+//!
+//! Requests and replies use the same framed JSON messages as CONFIG, on the FILEIO bulk
+//! endpoints. File contents are streamed as a series of `readFile` replies, each carrying
+//! `size` raw bytes after its JSON; a zero-sized reply marks the end of the file. Uploads
+//! are the same in reverse, as `writeFile` requests.
+
+use std::{io::Read, time::Duration};
+
+use flate2::read::DeflateDecoder;
+use serde_json::{json, Value};
+
+use crate::{transport::Transport, FlirError, FlirOne};
+
+/// Directory the camera saves shutter-button captures to.
+pub const STORED_IMAGES_DIR: &str = "Images";
+/// Archive of the unit's calibration and configuration files.
+pub const CAMERA_FILES: &str = "CameraFiles.zip";
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const WRITE_CHUNK: usize = 16384;
+
+/// How far a transfer has got, passed to the progress callbacks of [`FileSystem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub transferred: usize,
+    /// Size of the whole file, where the camera gives it.
+    pub total: Option<usize>,
+}
+
+/// The camera's files, see [`FlirOne::file_system`].
+pub struct FileSystem<'d, T: Transport> {
+    pub(crate) device: &'d mut FlirOne<T>,
+    pub(crate) read: u8,
+    pub(crate) write: u8,
+}
+
+impl<T: Transport> FileSystem<'_, T> {
+    pub fn list_dir(&mut self, path: &str) -> Result<Vec<String>, FlirError> {
+        let (data, _) = self.request("listDirectory", json!({ "path": path }), &[])?;
+        let files = data
+            .get("files")
+            .and_then(Value::as_array)
+            .ok_or_else(|| FlirError::Protocol("listDirectory reply has no files".into()))?;
+        Ok(files
+            .iter()
+            .filter_map(|f| f.as_str().map(str::to_owned))
+            .collect())
+    }
+
+    pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>, FlirError> {
+        self.read_file_with_progress(path, |_| {})
+    }
+
+    /// [`FileSystem::read_file`], calling `progress` after each chunk.
+    pub fn read_file_with_progress(
+        &mut self,
+        path: &str,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<Vec<u8>, FlirError> {
+        let (stream, total) = self.open(path, "r")?;
+        let mut contents = Vec::new();
+        loop {
+            let (data, chunk) =
+                self.request("readFile", json!({ "streamIdentifier": stream }), &[])?;
+            let size = data.get("size").and_then(Value::as_u64).unwrap_or(0) as usize;
+            if size == 0 {
+                break;
+            }
+            if chunk.len() < size {
+                return Err(FlirError::Protocol(format!(
+                    "readFile announced {size} bytes but carried {}",
+                    chunk.len()
+                )));
+            }
+            contents.extend_from_slice(&chunk[..size]);
+            progress(Progress {
+                transferred: contents.len(),
+                total,
+            });
+        }
+
+        self.request("closeFile", json!({ "streamIdentifier": stream }), &[])?;
+        Ok(contents)
+    }
+
+    /// Writes `contents` to `path`, replacing any file there. Firmware without uploads
+    /// rejects the request with [`FlirError::Protocol`].
+    pub fn write_file(&mut self, path: &str, contents: &[u8]) -> Result<(), FlirError> {
+        self.write_file_with_progress(path, contents, |_| {})
+    }
+
+    /// [`FileSystem::write_file`], calling `progress` after each chunk.
+    pub fn write_file_with_progress(
+        &mut self,
+        path: &str,
+        contents: &[u8],
+        mut progress: impl FnMut(Progress),
+    ) -> Result<(), FlirError> {
+        let (stream, _) = self.open(path, "w")?;
+        let mut transferred = 0;
+        for chunk in contents.chunks(WRITE_CHUNK) {
+            self.request(
+                "writeFile",
+                json!({ "streamIdentifier": stream, "size": chunk.len() }),
+                chunk,
+            )?;
+            transferred += chunk.len();
+            progress(Progress {
+                transferred,
+                total: Some(contents.len()),
+            });
+        }
+
+        self.request("closeFile", json!({ "streamIdentifier": stream }), &[])?;
+        Ok(())
+    }
+
+    /// Downloads [`CAMERA_FILES`] and unpacks it into `(name, contents)` pairs, in archive
+    /// order.
+    pub fn camera_files(
+        &mut self,
+        progress: impl FnMut(Progress),
+    ) -> Result<Vec<(String, Vec<u8>)>, FlirError> {
+        unzip(&self.read_file_with_progress(CAMERA_FILES, progress)?)
+    }
+
+    /// Opens `path` and returns its stream and, when the camera gives it, its size.
+    fn open(&mut self, path: &str, mode: &str) -> Result<(u64, Option<usize>), FlirError> {
+        let (data, _) = self.request("openFile", json!({ "mode": mode, "path": path }), &[])?;
+        let stream = data
+            .get("streamIdentifier")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| FlirError::Protocol("openFile reply has no streamIdentifier".into()))?;
+        let size = data.get("size").and_then(Value::as_u64).map(|s| s as usize);
+        Ok((stream, size))
+    }
+
+    fn request(
+        &mut self,
+        kind: &str,
+        data: Value,
+        payload: &[u8],
+    ) -> Result<(Value, Vec<u8>), FlirError> {
+        self.device
+            .request_with_payload(self.read, self.write, kind, data, payload, TIMEOUT)
+    }
+}
+
+const ZIP_END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP_DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_END_OF_DIRECTORY_LEN: usize = 22;
+
+/// Unpacks the stored and deflated entries of a zip archive, going by its central
+/// directory. Directories are skipped.
+fn unzip(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, FlirError> {
+    let invalid = |what: &str| FlirError::InvalidFormat(format!("{CAMERA_FILES}: {what}"));
+    let u16_at = |at: usize| {
+        archive
+            .get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(|| invalid("truncated"))
+    };
+    let u32_at = |at: usize| {
+        archive
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid("truncated"))
+    };
+
+    let end = (0..=archive.len().saturating_sub(ZIP_END_OF_DIRECTORY_LEN))
+        .rev()
+        .find(|&at| u32_at(at).ok() == Some(ZIP_END_OF_DIRECTORY))
+        .ok_or_else(|| invalid("no end of central directory"))?;
+    let count = u16_at(end + 10)?;
+    let mut entry = u32_at(end + 16)? as usize;
+
+    // Counts and sizes come from the archive, so nothing is allocated up front by them.
+    let mut files = Vec::new();
+    for _ in 0..count {
+        if u32_at(entry)? != ZIP_DIRECTORY_ENTRY {
+            return Err(invalid("bad central directory entry"));
+        }
+        let method = u16_at(entry + 10)?;
+        let compressed = u32_at(entry + 20)? as usize;
+        let name_len = u16_at(entry + 28)?;
+        let skip = name_len + u16_at(entry + 30)? + u16_at(entry + 32)?;
+        let local = u32_at(entry + 42)? as usize;
+        let name = archive
+            .get(entry + 46..entry + 46 + name_len)
+            .ok_or_else(|| invalid("truncated"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        entry += 46 + skip;
+        if name.ends_with('/') {
+            continue;
+        }
+
+        if u32_at(local)? != ZIP_LOCAL_HEADER {
+            return Err(invalid("bad local header"));
+        }
+        let start = local + 30 + u16_at(local + 26)? + u16_at(local + 28)?;
+        let data = start
+            .checked_add(compressed)
+            .and_then(|end| archive.get(start..end))
+            .ok_or_else(|| invalid("truncated"))?;
+        let contents = match method {
+            0 => data.to_vec(),
+            8 => {
+                let mut contents = Vec::new();
+                DeflateDecoder::new(data)
+                    .read_to_end(&mut contents)
+                    .map_err(|_| invalid(&format!("{name} does not inflate")))?;
+                contents
+            }
+            other => return Err(invalid(&format!("{name} uses compression method {other}"))),
+        };
+        files.push((name, contents));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::DeflateEncoder, Compression};
+
+    use super::*;
+
+    /// An archive of `(name, method, stored bytes)` entries, sizes taken from the bytes.
+    fn zip(entries: &[(&str, u16, &[u8])]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut directory = Vec::new();
+        for (name, method, data) in entries {
+            let mut local = vec![0u8; 30];
+            local[..4].copy_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+            local[26..28].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            let mut entry = vec![0u8; 46];
+            entry[..4].copy_from_slice(&ZIP_DIRECTORY_ENTRY.to_le_bytes());
+            entry[10..12].copy_from_slice(&method.to_le_bytes());
+            entry[20..24].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            entry[28..30].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            entry[42..46].copy_from_slice(&(zip.len() as u32).to_le_bytes());
+            zip.extend(local);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(data);
+            directory.extend(entry);
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let mut end = vec![0u8; ZIP_END_OF_DIRECTORY_LEN];
+        end[..4].copy_from_slice(&ZIP_END_OF_DIRECTORY.to_le_bytes());
+        end[10..12].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+        end[16..20].copy_from_slice(&(zip.len() as u32).to_le_bytes());
+        zip.extend(directory);
+        zip.extend(end);
+        zip
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn unzips_stored_and_deflated_entries() {
+        let text = b"planck constants ".repeat(20);
+        let archive = zip(&[
+            ("conf/", 0, b""),
+            ("conf/a.rsc", 0, b"stored"),
+            ("conf/b.rsc", 8, &deflate(&text)),
+        ]);
+        assert_eq!(
+            unzip(&archive).unwrap(),
+            [
+                ("conf/a.rsc".to_owned(), b"stored".to_vec()),
+                ("conf/b.rsc".to_owned(), text),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_archives() {
+        let archive = zip(&[("a.rsc", 0, b"stored")]);
+        for len in [0, 10, archive.len() - 1] {
+            assert!(matches!(
+                unzip(&archive[..len]),
+                Err(FlirError::InvalidFormat(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_sizes_and_offsets_past_the_end() {
+        let archive = zip(&[("a.rsc", 0, b"stored")]);
+        let directory = archive.len() - ZIP_END_OF_DIRECTORY_LEN - (46 + "a.rsc".len());
+        for (field, value) in [(20, u32::MAX), (42, u32::MAX - 30), (42, 1 << 20)] {
+            let mut corrupt = archive.clone();
+            corrupt[directory + field..directory + field + 4].copy_from_slice(&value.to_le_bytes());
+            assert!(matches!(unzip(&corrupt), Err(FlirError::InvalidFormat(_))));
+        }
+
+        let mut count = archive.clone();
+        let end = archive.len() - ZIP_END_OF_DIRECTORY_LEN;
+        count[end + 10..end + 12].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(matches!(unzip(&count), Err(FlirError::InvalidFormat(_))));
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// The lens field of view, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fov {
+    pub horizontal: f32,
+    pub vertical: f32,
+}
+
+impl Fov {
+    /// FLIR One Gen 3, from its datasheet (±1°).
+    pub const FLIR_ONE: Fov = Fov {
+        horizontal: 50.0,
+        vertical: 38.0,
+    };
+    /// FLIR One Pro, from its datasheet (±1°).
+    pub const FLIR_ONE_PRO: Fov = Fov {
+        horizontal: 55.0,
+        vertical: 43.0,
+    };
+
+    /// The datasheet field of view of the model named by a USB product string.
+    pub fn for_product(product: Option<&str>) -> Fov {
+        match product {
+            Some(product) if product.contains("Pro") => Fov::FLIR_ONE_PRO,
+            _ => Fov::FLIR_ONE,
+        }
+    }
+
+    /// The width and height in metres a pixel covers at `distance` metres, for an image
+    /// of `width` by `height` pixels.
+    pub fn pixel_size(&self, distance: f32, width: u32, height: u32) -> (f32, f32) {
+        let extent = |degrees: f32| 2.0 * distance * (degrees.to_radians() / 2.0).tan();
+        (
+            extent(self.horizontal) / width as f32,
+            extent(self.vertical) / height as f32,
+        )
+    }
+}
@@ -0,0 +1,394 @@
+//! The FRAME protocol: each frame is a 28-byte header followed by the raw thermal plane,
+//! the visual camera's JPEG and a JSON status blob, in that order. Frames are larger than
+//! a single bulk transfer and are reassembled by [`FrameParser`].
+
+use std::{fs, path::Path, time::Duration};
+
+use image::{ImageFormat, RgbImage};
+
+use crate::{
+    radiometry::TemperatureMap, Calibration, ColorMode, ColorScale, FlirError, Orientation,
+    Palette, ThermalImage,
+};
+
+pub const FRAME_MAGIC: [u8; 4] = [0xef, 0xbe, 0x00, 0x00];
+pub const HEADER_LEN: usize = 28;
+
+/// Largest frame, header included, the parser accepts. Real frames are a fraction of this
+/// even with a large visual JPEG; a header announcing more is corrupt, and waiting for it
+/// would buffer up to 4 GB.
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+
+pub const THERMAL_WIDTH: u32 = 160;
+pub const THERMAL_HEIGHT: u32 = 120;
+
+/// A thermal plane with less spread than this, in raw counts, is taken to be the closed
+/// shutter. Real scenes vary by far more, if only through sensor noise and vignetting.
+const SHUTTER_MAX_STD_DEV: f64 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Bytes following the header.
+    pub frame_size: u32,
+    pub thermal_size: u32,
+    pub jpeg_size: u32,
+    pub status_size: u32,
+}
+
+impl FrameHeader {
+    pub fn parse(bytes: &[u8]) -> Result<FrameHeader, FlirError> {
+        FrameHeader::try_from(bytes)
+    }
+
+    /// Length of the whole frame, header included.
+    pub fn total_len(&self) -> usize {
+        HEADER_LEN + self.frame_size as usize
+    }
+}
+
+impl TryFrom<&[u8]> for FrameHeader {
+    type Error = FlirError;
+
+    fn try_from(bytes: &[u8]) -> Result<FrameHeader, FlirError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(FlirError::InvalidFormat(format!(
+                "frame header needs {HEADER_LEN} bytes, got {}",
+                bytes.len()
+            )));
+        }
+        if bytes[..4] != FRAME_MAGIC {
+            return Err(FlirError::InvalidFormat(format!(
+                "bad frame magic {:02x?}",
+                &bytes[..4]
+            )));
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let header = FrameHeader {
+            frame_size: u32_at(8),
+            thermal_size: u32_at(12),
+            jpeg_size: u32_at(16),
+            status_size: u32_at(20),
+        };
+        if header.total_len() > MAX_FRAME_LEN {
+            return Err(FlirError::InvalidFormat(format!(
+                "frame size {} exceeds the {MAX_FRAME_LEN} byte maximum",
+                header.frame_size
+            )));
+        }
+        let sections =
+            header.thermal_size as u64 + header.jpeg_size as u64 + header.status_size as u64;
+        if sections > header.frame_size as u64 {
+            return Err(FlirError::InvalidFormat(format!(
+                "frame sections ({sections} bytes) exceed frame size ({})",
+                header.frame_size
+            )));
+        }
+        Ok(header)
+    }
+}
+
+/// A complete frame borrowed from the buffer it arrived in, for looking at frames without
+/// copying their sections out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRef<'a> {
+    pub header: FrameHeader,
+    pub thermal_raw: &'a [u8],
+    pub visual_jpeg: &'a [u8],
+    /// The status section including any NUL padding.
+    pub status: &'a [u8],
+}
+
+impl<'a> FrameRef<'a> {
+    /// Splits a complete frame, header included, into its sections.
+    pub fn parse(bytes: &'a [u8]) -> Result<FrameRef<'a>, FlirError> {
+        FrameRef::try_from(bytes)
+    }
+
+    pub fn to_frame(&self) -> Frame {
+        let status = String::from_utf8_lossy(self.status);
+        Frame {
+            header: self.header,
+            thermal_raw: self.thermal_raw.to_vec(),
+            visual_jpeg: self.visual_jpeg.to_vec(),
+            status_json: status.trim_end_matches('\0').to_owned(),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for FrameRef<'a> {
+    type Error = FlirError;
+
+    fn try_from(bytes: &'a [u8]) -> Result<FrameRef<'a>, FlirError> {
+        let header = FrameHeader::try_from(bytes)?;
+        if bytes.len() < header.total_len() {
+            return Err(FlirError::InvalidFormat(format!(
+                "frame is {} bytes, header announces {}",
+                bytes.len(),
+                header.total_len()
+            )));
+        }
+        let thermal_end = HEADER_LEN + header.thermal_size as usize;
+        let jpeg_end = thermal_end + header.jpeg_size as usize;
+        let status_end = jpeg_end + header.status_size as usize;
+        Ok(FrameRef {
+            header,
+            thermal_raw: &bytes[HEADER_LEN..thermal_end],
+            visual_jpeg: &bytes[thermal_end..jpeg_end],
+            status: &bytes[jpeg_end..status_end],
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub header: FrameHeader,
+    pub thermal_raw: Vec<u8>,
+    pub visual_jpeg: Vec<u8>,
+    pub status_json: String,
+}
+
+impl Frame {
+    /// Splits a complete frame, header included, into its sections.
+    pub fn parse(bytes: &[u8]) -> Result<Frame, FlirError> {
+        FrameRef::try_from(bytes).map(|frame| frame.to_frame())
+    }
+
+    /// The frame in the layout it arrived in, so it can be recorded and parsed again.
+    /// Header fields the driver does not interpret are written as zero.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.header.total_len());
+        bytes.extend_from_slice(&FRAME_MAGIC);
+        bytes.extend_from_slice(&[0; 4]);
+        for size in [
+            self.header.frame_size,
+            self.header.thermal_size,
+            self.header.jpeg_size,
+            self.header.status_size,
+        ] {
+            bytes.extend_from_slice(&size.to_le_bytes());
+        }
+        bytes.resize(HEADER_LEN, 0);
+        bytes.extend_from_slice(&self.thermal_raw);
+        bytes.extend_from_slice(&self.visual_jpeg);
+        let status_start = bytes.len();
+        bytes.extend_from_slice(self.status_json.as_bytes());
+        bytes.resize(status_start + self.header.status_size as usize, 0);
+        bytes.resize(self.header.total_len(), 0);
+        bytes
+    }
+
+    /// Whether the frame was taken with the shutter closed, during a NUC. Uses the status
+    /// blob's shutter and FFC state when present, otherwise how flat the thermal plane is.
+    pub fn is_shutter_frame(&self) -> bool {
+        if let Ok(status) = serde_json::from_str::<serde_json::Value>(&self.status_json) {
+            let state = |key| status.get(key).and_then(serde_json::Value::as_str);
+            match (state("shutterState"), state("ffcState")) {
+                (Some(shutter), _) if shutter != "ON" => return true,
+                (_, Some("FFC_PROGRESS")) => return true,
+                (Some(_), _) | (_, Some(_)) => return false,
+                (None, None) => {}
+            }
+        }
+        match ThermalImage::from_frame(self) {
+            Ok(image) => image.std_dev() < SHUTTER_MAX_STD_DEV,
+            Err(_) => false,
+        }
+    }
+
+    /// When the camera queued the frame for USB, from the status blob's
+    /// `usbNotifiedTimestamp` (seconds on the camera clock).
+    pub fn timestamp(&self) -> Option<Duration> {
+        let status: serde_json::Value = serde_json::from_str(&self.status_json).ok()?;
+        let seconds = status.get("usbNotifiedTimestamp")?.as_f64()?;
+        Duration::try_from_secs_f64(seconds).ok()
+    }
+
+    /// The camera's running frame count from the status blob's `frameCount`. It is a
+    /// 32-bit counter and wraps.
+    pub fn counter(&self) -> Option<u32> {
+        let status: serde_json::Value = serde_json::from_str(&self.status_json).ok()?;
+        let count = status.get("frameCount")?.as_u64()?;
+        Some(count as u32)
+    }
+
+    /// The camera's orientation from the status blob, on firmware that reports it.
+    pub fn orientation(&self) -> Option<Orientation> {
+        let status: serde_json::Value = serde_json::from_str(&self.status_json).ok()?;
+        Orientation::from_degrees(status.get("orientation")?.as_f64()?)
+    }
+
+    /// Decodes the thermal plane and converts it to temperatures with `cal`. Scene
+    /// parameters such as emissivity and reflected temperature come from `cal.object`,
+    /// see [`Calibration::with_object`].
+    pub fn temperatures(&self, cal: &Calibration) -> Result<TemperatureMap, FlirError> {
+        let image = ThermalImage::from_frame(self)?;
+        Ok(TemperatureMap::new(
+            image.width(),
+            image.height(),
+            image.to_celsius(cal),
+        ))
+    }
+
+    /// Decodes the thermal plane and colorizes it, stretched over the frame's own range of
+    /// raw counts. For live display use [`Frame::render_scaled`] with a
+    /// [`ColorScale::Agc`], which keeps the colors from flickering between frames.
+    pub fn render(&self, palette: Palette) -> Result<RgbImage, FlirError> {
+        Ok(ThermalImage::from_frame(self)?.colorize(palette))
+    }
+
+    /// [`Frame::render`] over the range `scale` picks, see
+    /// [`ThermalImage::colorize_scaled`].
+    pub fn render_scaled(
+        &self,
+        palette: Palette,
+        scale: &mut ColorScale,
+    ) -> Result<RgbImage, FlirError> {
+        Ok(ThermalImage::from_frame(self)?.colorize_scaled(palette, scale))
+    }
+
+    /// Saves the thermal plane as a PNG in `mode`: a [`Palette`] as [`Frame::render`], or
+    /// an isotherm as [`ThermalImage::render`], which needs `cal` to find its pixels.
+    pub fn save_png(
+        &self,
+        path: &Path,
+        mode: impl Into<ColorMode>,
+        cal: Option<&Calibration>,
+    ) -> Result<(), FlirError> {
+        let image = ThermalImage::from_frame(self)?;
+        let png = match (mode.into(), cal) {
+            (ColorMode::Palette(palette), _) => image.to_png(palette)?,
+            (mode, Some(cal)) => image.render_png(mode, cal)?,
+            (_, None) => {
+                return Err(FlirError::NoCalibration(
+                    "an isotherm needs one to find its pixels".to_owned(),
+                ))
+            }
+        };
+        fs::write(path, png)?;
+        Ok(())
+    }
+
+    /// Saves the raw thermal counts as a 16-bit grayscale TIFF, see
+    /// [`ThermalImage::to_tiff16`]. Temperatures can be recovered from it with the
+    /// camera's [`Calibration`].
+    pub fn save_tiff16(&self, path: &Path) -> Result<(), FlirError> {
+        fs::write(path, ThermalImage::from_frame(self)?.to_tiff16())?;
+        Ok(())
+    }
+
+    /// Decodes the visual camera's JPEG.
+    pub fn visual_image(&self) -> Result<RgbImage, FlirError> {
+        let image = image::load_from_memory_with_format(&self.visual_jpeg, ImageFormat::Jpeg)?;
+        Ok(image.into_rgb8())
+    }
+}
+
+/// Reassembles frames from bulk transfers. Bytes before a frame magic are discarded, and
+/// each frame is exactly the length its header gives.
+#[derive(Debug, Default)]
+pub struct FrameParser {
+    buf: Vec<u8>,
+    resyncs: u64,
+    /// A frame was returned before any bytes after it had arrived, so the start of the
+    /// buffer is still to be checked for the next frame's magic.
+    check_boundary: bool,
+}
+
+impl FrameParser {
+    pub fn new() -> Self {
+        FrameParser::default()
+    }
+
+    /// Bytes held back waiting for the rest of a frame.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Bytes still missing from the frame at the front of the buffer, once its header has
+    /// arrived and [`FrameParser::next_frame`] has aligned the buffer to it.
+    pub fn missing(&self) -> Option<usize> {
+        if !self.buf.starts_with(&FRAME_MAGIC) {
+            return None;
+        }
+        let header = FrameHeader::parse(&self.buf).ok()?;
+        Some(header.total_len().saturating_sub(self.buf.len()))
+    }
+
+    /// Returns the next complete frame, or `None` until enough data has been pushed.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, FlirError> {
+        match self.next_raw_frame()? {
+            Some(bytes) => Frame::parse(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// [`FrameParser::next_frame`] without splitting the frame: its bytes, header
+    /// included, once the magic and the header's sizes have been checked.
+    pub fn next_raw_frame(&mut self) -> Result<Option<Vec<u8>>, FlirError> {
+        if self.check_boundary && !self.buf.is_empty() {
+            self.check_boundary = false;
+            let peek = self.buf.len().min(FRAME_MAGIC.len());
+            if self.buf[..peek] != FRAME_MAGIC[..peek] {
+                self.resyncs += 1;
+                return Err(FlirError::InvalidFormat(
+                    "previous frame was not followed by a frame magic".into(),
+                ));
+            }
+        }
+        match self
+            .buf
+            .windows(FRAME_MAGIC.len())
+            .position(|w| w == FRAME_MAGIC)
+        {
+            Some(start) => {
+                self.buf.drain(..start);
+            }
+            None => {
+                let keep = self.buf.len().min(FRAME_MAGIC.len() - 1);
+                self.buf.drain(..self.buf.len() - keep);
+                return Ok(None);
+            }
+        }
+        if self.buf.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        let header = match FrameHeader::parse(&self.buf) {
+            Ok(header) => header,
+            Err(e) => {
+                // Not a real frame start; skip past this magic so the next call resyncs.
+                self.buf.drain(..FRAME_MAGIC.len());
+                return Err(e);
+            }
+        };
+        let total = header.total_len();
+        if self.buf.len() < total {
+            return Ok(None);
+        }
+        // The header's length is authoritative, so whatever follows the frame must be
+        // the start of the next one. If it is not, the length was wrong. Reads usually
+        // end exactly at the frame, in which case the check waits for the next call.
+        let next = &self.buf[total..];
+        self.check_boundary = next.len() < FRAME_MAGIC.len();
+        let peek = next.len().min(FRAME_MAGIC.len());
+        if next[..peek] != FRAME_MAGIC[..peek] {
+            self.buf.drain(..FRAME_MAGIC.len());
+            self.resyncs += 1;
+            return Err(FlirError::InvalidFormat(format!(
+                "frame of {total} bytes is not followed by a frame magic"
+            )));
+        }
+        Ok(Some(self.buf.drain(..total).collect()))
+    }
+
+    /// Frames whose length was found wrong because the bytes after them did not start a
+    /// new frame. Frames are dropped when this shows before they are returned, otherwise
+    /// the next call reports it.
+    pub fn resyncs(&self) -> u64 {
+        self.resyncs
+    }
+}
@@ -0,0 +1,119 @@
+//! Temperature-based rendering: contour lines at fixed temperatures, traced with marching
+//! squares, and alarm ranges painted over grayscale.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, Rgb, RgbImage};
+
+use crate::{palette::ColorMode, Calibration, FlirError, Palette, ThermalImage};
+
+const LINE_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+
+impl ThermalImage {
+    /// Renders the image in `mode`, which has to pass [`ColorMode::check`].
+    /// [`ColorMode::Palette`] is [`ThermalImage::colorize`]; `cal` is only used to find the
+    /// pixels of a [`ColorMode::Isotherm`].
+    pub fn render(&self, mode: ColorMode, cal: &Calibration) -> Result<RgbImage, FlirError> {
+        mode.check()?;
+        let (palette, color) = match mode {
+            ColorMode::Palette(palette) => return Ok(self.colorize(palette)),
+            ColorMode::Isotherm { color, .. } => (Palette::Grayscale, color),
+        };
+        let mut image = self.colorize(palette);
+        let temps = self.to_celsius(cal);
+        for (pixel, &celsius) in image.pixels_mut().zip(&temps) {
+            if mode.is_alarm(celsius) {
+                *pixel = Rgb(color);
+            }
+        }
+        Ok(image)
+    }
+
+    /// [`ThermalImage::render`] encoded as PNG.
+    pub fn render_png(&self, mode: ColorMode, cal: &Calibration) -> Result<Vec<u8>, FlirError> {
+        let mut png = Cursor::new(Vec::new());
+        self.render(mode, cal)?
+            .write_to(&mut png, ImageFormat::Png)?;
+        Ok(png.into_inner())
+    }
+
+    /// [`ThermalImage::colorize`] with a white contour line at each of `levels`, in
+    /// degrees Celsius.
+    pub fn render_isotherms(
+        &self,
+        levels: &[f32],
+        cal: &Calibration,
+        palette: Palette,
+    ) -> RgbImage {
+        let mut image = self.colorize(palette);
+        let temps = self.to_celsius(cal);
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let at = |x: usize, y: usize| temps[y * width + x];
+
+        for &level in levels {
+            for y in 0..height.saturating_sub(1) {
+                for x in 0..width.saturating_sub(1) {
+                    let corners = [
+                        ((x, y), at(x, y)),
+                        ((x + 1, y), at(x + 1, y)),
+                        ((x + 1, y + 1), at(x + 1, y + 1)),
+                        ((x, y + 1), at(x, y + 1)),
+                    ];
+                    for (from, to) in cell_segments(corners, level) {
+                        draw_line(&mut image, from, to);
+                    }
+                }
+            }
+        }
+        image
+    }
+}
+
+type Point = (f32, f32);
+
+/// The contour segments crossing one cell, whose corners are given clockwise from the
+/// top left.
+fn cell_segments(corners: [((usize, usize), f32); 4], level: f32) -> Vec<(Point, Point)> {
+    // Crossing points on the top, right, bottom and left edges, in that order.
+    let mut crossings = Vec::with_capacity(4);
+    for i in 0..4 {
+        let ((ax, ay), a) = corners[i];
+        let ((bx, by), b) = corners[(i + 1) % 4];
+        if (a >= level) != (b >= level) {
+            let t = (level - a) / (b - a);
+            crossings.push((
+                ax as f32 + t * (bx as f32 - ax as f32),
+                ay as f32 + t * (by as f32 - ay as f32),
+            ));
+        }
+    }
+    match crossings[..] {
+        [p, q] => vec![(p, q)],
+        // A saddle: the centre decides which pairs of edges are joined.
+        [top, right, bottom, left] => {
+            let centre = corners.iter().map(|&(_, v)| v).sum::<f32>() / 4.0;
+            if (centre >= level) == (corners[0].1 >= level) {
+                vec![(top, right), (bottom, left)]
+            } else {
+                vec![(top, left), (bottom, right)]
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn draw_line(image: &mut RgbImage, from: Point, to: Point) {
+    let steps = (to.0 - from.0)
+        .abs()
+        .max((to.1 - from.1).abs())
+        .ceil()
+        .max(1.0) as u32;
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let x = (from.0 + t * (to.0 - from.0)).round() as u32;
+        let y = (from.1 + t * (to.1 - from.1)).round() as u32;
+        if x < image.width() && y < image.height() {
+            image.put_pixel(x, y, LINE_COLOR);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! A userspace driver for the FLIR One thermal camera: USB setup ([`FlirOneBuilder`]),
+//! the CONFIG, FILEIO and FRAME protocols ([`FlirOne`], [`ProtocolType`]), and decoding
+//! and radiometry for the frames it sends.
+
+mod cancel;
+pub mod capture;
+mod claim;
+pub mod config;
+pub mod descriptor;
+mod device;
+pub mod endpoints;
+mod error;
+pub mod fff;
+pub mod fileio;
+mod fov;
+pub mod frame;
+mod isotherm;
+mod message;
+mod motion;
+pub mod palette;
+pub mod profiles;
+pub mod radiometry;
+mod retry;
+pub mod sink;
+mod stats;
+mod status;
+mod stream;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+mod thermal;
+mod tiff;
+#[cfg(feature = "transfer-log")]
+pub mod transfer_log;
+pub mod transport;
+pub mod visual;
+
+pub use cancel::CancelToken;
+pub use claim::ClaimedInterfaces;
+pub use descriptor::{ConfigSummary, DeviceDescription, DeviceInfo, StringDescriptors};
+pub use device::{
+    FlirOne, FlirOneBuilder, ProtocolType, CONFIG_READ_SIZE, FLIR_ONE_PRODUCT_IDS, FLIR_VENDOR_ID,
+    MAX_FRAME_SIZE,
+};
+pub use error::FlirError;
+pub use fov::Fov;
+pub use frame::{Frame, FrameHeader, FrameRef};
+pub use message::ConfigCodec;
+pub use motion::MotionDetector;
+pub use palette::{AgcState, ColorMode, ColorScale, Palette, PaletteLut};
+pub use radiometry::{Calibration, TempUnit, TemperatureMap};
+pub use retry::RetryPolicy;
+pub use sink::FrameSink;
+pub use stats::{Extreme, FrameStats, SessionStats};
+pub use status::CameraStatus;
+pub use stream::{BoundedStream, FrameStream, PacedStream};
+pub use thermal::{BadPixelMap, Endianness, Orientation, Rect, Rotation, ThermalImage};
+pub use transport::{MockTransport, Transport};
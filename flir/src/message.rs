@@ -0,0 +1,137 @@
+//! Framing shared by the CONFIG and FILEIO bulk endpoints: a 16-byte header followed by a
+//! NUL-terminated JSON document and, for some replies, raw binary data.
+
+use crate::FlirError;
+
+pub const HEADER_LEN: usize = 16;
+/// Largest payload a header may announce. Far more than any CONFIG reply or FILEIO chunk,
+/// so a larger one means the header is corrupt.
+pub const MAX_MESSAGE_LEN: usize = 1 << 20;
+const MAGIC: [u8; 8] = [0xcc, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+
+/// Frames `json` and any binary `payload`, which follows the JSON's NUL as file contents
+/// do.
+pub fn encode(json: &str, payload: &[u8]) -> Vec<u8> {
+    let len = (json.len() + 1 + payload.len()) as u32;
+    let mut out = Vec::with_capacity(HEADER_LEN + len as usize);
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&len.to_le_bytes());
+    // The last header word differs between messages but the camera accepts zero.
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(json.as_bytes());
+    out.push(0);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Payload length announced by a header, or `None` if fewer than `HEADER_LEN` bytes are
+/// available yet.
+pub fn payload_len(bytes: &[u8]) -> Result<Option<usize>, FlirError> {
+    if bytes.len() < HEADER_LEN {
+        return Ok(None);
+    }
+    if bytes[..MAGIC.len()] != MAGIC {
+        return Err(FlirError::Protocol(format!(
+            "bad message header {:02x?}",
+            &bytes[..HEADER_LEN]
+        )));
+    }
+    let len = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(FlirError::Protocol(format!(
+            "message header announces {len} bytes, more than {MAX_MESSAGE_LEN}"
+        )));
+    }
+    Ok(Some(len))
+}
+
+/// Splits a payload into its JSON document and whatever binary data follows the NUL.
+pub fn split_payload(payload: &[u8]) -> Result<(serde_json::Value, &[u8]), FlirError> {
+    let end = payload
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(payload.len());
+    let json = serde_json::from_slice(&payload[..end])?;
+    let rest = payload.get(end + 1..).unwrap_or(&[]);
+    Ok((json, rest))
+}
+
+/// Reassembles messages from bulk transfers, which may end mid-message or carry several.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigCodec {
+    buf: Vec<u8>,
+}
+
+impl ConfigCodec {
+    pub fn new() -> Self {
+        ConfigCodec::default()
+    }
+
+    /// Bytes received that are not part of a complete message yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Bytes still missing from the message at the front of the buffer, once its header
+    /// has arrived.
+    pub fn missing(&self) -> Option<usize> {
+        let len = payload_len(&self.buf).ok()??;
+        Some((HEADER_LEN + len).saturating_sub(self.buf.len()))
+    }
+
+    /// Takes the payload of the next complete message, if there is one. A corrupt header,
+    /// bad magic or a length over [`MAX_MESSAGE_LEN`], drops everything buffered, as there
+    /// is no way to find the next message in it.
+    pub fn next_message(&mut self) -> Result<Option<Vec<u8>>, FlirError> {
+        let len = match payload_len(&self.buf) {
+            Ok(Some(len)) => len,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                self.buf.clear();
+                return Err(e);
+            }
+        };
+        if self.buf.len() < HEADER_LEN + len {
+            return Ok(None);
+        }
+        let payload = self.buf[HEADER_LEN..HEADER_LEN + len].to_vec();
+        self.buf.drain(..HEADER_LEN + len);
+        Ok(Some(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reassembles_messages_split_and_joined() {
+        let mut bytes = encode("{\"a\":1}", b"");
+        bytes.extend(encode("{}", b"data"));
+        let mut codec = ConfigCodec::new();
+        codec.push(&bytes[..5]);
+        assert_eq!(codec.next_message().unwrap(), None);
+        codec.push(&bytes[5..]);
+        assert_eq!(codec.next_message().unwrap().unwrap(), b"{\"a\":1}\0");
+        assert_eq!(codec.next_message().unwrap().unwrap(), b"{}\0data");
+        assert_eq!(codec.buffered(), 0);
+    }
+
+    #[test]
+    fn rejects_oversized_messages_instead_of_buffering() {
+        let mut header = encode("{}", b"");
+        header[8..12].copy_from_slice(&(MAX_MESSAGE_LEN as u32 + 1).to_le_bytes());
+        let mut codec = ConfigCodec::new();
+        codec.push(&header);
+        assert_eq!(codec.missing(), None);
+        assert!(matches!(codec.next_message(), Err(FlirError::Protocol(_))));
+        assert_eq!(codec.buffered(), 0);
+
+        header[8..12].copy_from_slice(&(MAX_MESSAGE_LEN as u32).to_le_bytes());
+        assert_eq!(payload_len(&header).unwrap(), Some(MAX_MESSAGE_LEN));
+    }
+}
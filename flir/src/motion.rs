@@ -0,0 +1,58 @@
+//! Triggering on change between consecutive thermal images.
+
+use crate::{Rect, ThermalImage};
+
+/// Compares each image with the one before and reports the region that changed, when
+/// enough of it did.
+#[derive(Debug, Clone)]
+pub struct MotionDetector {
+    threshold: u16,
+    ratio: f32,
+    previous: Option<ThermalImage>,
+}
+
+impl MotionDetector {
+    /// A pixel has changed when it differs by more than `threshold` raw counts, and the
+    /// detector fires when more than `ratio` (0.0 to 1.0) of the pixels have.
+    pub fn new(threshold: u16, ratio: f32) -> Self {
+        MotionDetector {
+            threshold,
+            ratio,
+            previous: None,
+        }
+    }
+
+    /// Compares `image` with the previous one and keeps it for the next call. Returns the
+    /// bounding box of the changed pixels if the detector fires. The first image, and one
+    /// whose dimensions differ from the previous, never fire.
+    pub fn update(&mut self, image: &ThermalImage) -> Option<Rect> {
+        let previous = self.previous.replace(image.clone())?;
+        if (previous.width(), previous.height()) != (image.width(), image.height()) {
+            return None;
+        }
+
+        let width = image.width();
+        let mut changed = 0usize;
+        let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+        for (i, (&a, &b)) in image.pixels().iter().zip(previous.pixels()).enumerate() {
+            if a.abs_diff(b) <= self.threshold {
+                continue;
+            }
+            changed += 1;
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+
+        let fraction = changed as f32 / image.pixels().len() as f32;
+        (changed > 0 && fraction > self.ratio)
+            .then(|| Rect::new(left, top, right - left + 1, bottom - top + 1))
+    }
+
+    /// Forgets the previous image, so the next one is compared with nothing.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+}
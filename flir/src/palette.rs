@@ -0,0 +1,250 @@
+use std::str::FromStr;
+
+use crate::FlirError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    Grayscale,
+    /// Black through purple, red and yellow to white. An approximation of FLIR's Iron, not
+    /// a copy: there is no built-in Iron table, as none could be checked against FLIR Tools.
+    /// [`fff::parse_palette`](crate::fff::parse_palette) recovers the table a radiometric
+    /// JPEG was rendered with.
+    Ironbow,
+    /// Blue through green and yellow to red.
+    Rainbow,
+    /// Blues for the cold end, gold to white for the hot end.
+    Arctic,
+    /// Blue below zero, white at zero, red above. Meant for difference images.
+    Diverging,
+}
+
+impl FromStr for Palette {
+    type Err = FlirError;
+
+    /// Parses a palette's lowercase name, e.g. `ironbow`.
+    fn from_str(name: &str) -> Result<Self, FlirError> {
+        match name {
+            "grayscale" => Ok(Palette::Grayscale),
+            "ironbow" => Ok(Palette::Ironbow),
+            "rainbow" => Ok(Palette::Rainbow),
+            "arctic" => Ok(Palette::Arctic),
+            "diverging" => Ok(Palette::Diverging),
+            _ => Err(FlirError::InvalidFormat(format!(
+                "unknown palette {name:?}"
+            ))),
+        }
+    }
+}
+
+/// How [`ThermalImage::render`](crate::ThermalImage::render) turns temperatures into colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    Palette(Palette),
+    /// Grayscale, with pixels in the alarm range painted `color`. Temperatures are in
+    /// degrees Celsius. With both bounds set the range is between them, `above < c <
+    /// below`, so `above` has to be the lower one; see [`ColorMode::check`].
+    Isotherm {
+        below: Option<f32>,
+        above: Option<f32>,
+        color: [u8; 3],
+    },
+}
+
+impl ColorMode {
+    /// Rejects an [`ColorMode::Isotherm`] that could never paint a pixel: a bound that is
+    /// not a finite number, or both bounds set with `below` not greater than `above`.
+    pub fn check(&self) -> Result<(), FlirError> {
+        let ColorMode::Isotherm { below, above, .. } = *self else {
+            return Ok(());
+        };
+        if let Some(bad) = [below, above]
+            .into_iter()
+            .flatten()
+            .find(|t| !t.is_finite())
+        {
+            return Err(FlirError::InvalidFormat(format!(
+                "isotherm bound {bad} is not a temperature"
+            )));
+        }
+        if let (Some(below), Some(above)) = (below, above) {
+            if below <= above {
+                return Err(FlirError::InvalidFormat(format!(
+                    "isotherm between {above} and {below} is empty; `above` is the lower bound"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_alarm(&self, celsius: f32) -> bool {
+        match *self {
+            ColorMode::Palette(_) => false,
+            ColorMode::Isotherm { below, above, .. } => {
+                below.is_none_or(|below| celsius < below)
+                    && above.is_none_or(|above| celsius > above)
+                    && (below.is_some() || above.is_some())
+            }
+        }
+    }
+}
+
+impl From<Palette> for ColorMode {
+    fn from(palette: Palette) -> Self {
+        ColorMode::Palette(palette)
+    }
+}
+
+/// How raw counts are stretched over a palette by
+/// [`ThermalImage::colorize_scaled`](crate::ThermalImage::colorize_scaled).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorScale {
+    /// Each frame's own minimum and maximum, as [`ThermalImage::colorize`](crate::ThermalImage::colorize).
+    Auto,
+    /// The same raw count range for every frame.
+    Fixed { min: u16, max: u16 },
+    /// Each frame's range smoothed into the previous ones, see [`AgcState`].
+    Agc(AgcState),
+}
+
+/// Automatic gain control: an exponential moving average of the frames' minimum and
+/// maximum, which follows scene changes without the flicker of per-frame scaling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AgcState {
+    smoothing: f32,
+    range: Option<(f32, f32)>,
+}
+
+impl AgcState {
+    /// `smoothing` is the weight of each new frame's range, from near `0.0` (very stable)
+    /// to `1.0` (plain per-frame scaling).
+    pub fn new(smoothing: f32) -> Self {
+        AgcState {
+            smoothing: smoothing.clamp(f32::EPSILON, 1.0),
+            range: None,
+        }
+    }
+
+    /// Folds in a frame's range and returns the smoothed one. The first frame is taken
+    /// as is.
+    pub fn update(&mut self, min: f32, max: f32) -> (f32, f32) {
+        let range = match self.range {
+            Some((low, high)) => (
+                low + self.smoothing * (min - low),
+                high + self.smoothing * (max - high),
+            ),
+            None => (min, max),
+        };
+        self.range = Some(range);
+        range
+    }
+
+    /// The smoothed range, once a frame has been seen.
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.range
+    }
+
+    pub fn reset(&mut self) {
+        self.range = None;
+    }
+}
+
+impl Default for AgcState {
+    fn default() -> Self {
+        AgcState::new(0.1)
+    }
+}
+
+// Gradients as evenly spaced stops, coldest first.
+const IRONBOW: [[f32; 3]; 6] = [
+    [0.0, 0.0, 0.0],
+    [32.0, 0.0, 140.0],
+    [204.0, 0.0, 119.0],
+    [255.0, 120.0, 0.0],
+    [255.0, 215.0, 0.0],
+    [255.0, 255.0, 255.0],
+];
+const RAINBOW: [[f32; 3]; 5] = [
+    [0.0, 0.0, 255.0],
+    [0.0, 255.0, 255.0],
+    [0.0, 255.0, 0.0],
+    [255.0, 255.0, 0.0],
+    [255.0, 0.0, 0.0],
+];
+const ARCTIC: [[f32; 3]; 5] = [
+    [0.0, 0.0, 40.0],
+    [20.0, 60.0, 170.0],
+    [90.0, 200.0, 230.0],
+    [240.0, 180.0, 40.0],
+    [255.0, 255.0, 230.0],
+];
+const DIVERGING: [[f32; 3]; 3] = [
+    [59.0, 76.0, 192.0],
+    [221.0, 221.0, 221.0],
+    [180.0, 4.0, 38.0],
+];
+
+impl Palette {
+    pub fn is_diverging(&self) -> bool {
+        matches!(self, Palette::Diverging)
+    }
+
+    /// Maps `t` in `0.0..=1.0` to a color. Values outside the range are clamped.
+    pub fn color(&self, t: f32) -> [u8; 3] {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Palette::Grayscale => {
+                let v = (t * 255.0).round() as u8;
+                [v, v, v]
+            }
+            Palette::Ironbow => gradient(&IRONBOW, t),
+            Palette::Rainbow => gradient(&RAINBOW, t),
+            Palette::Arctic => gradient(&ARCTIC, t),
+            Palette::Diverging => gradient(&DIVERGING, t),
+        }
+    }
+}
+
+/// A palette given as a table of colors, such as the one FLIR stores in its radiometric
+/// JPEGs (see [`fff::parse_palette`](crate::fff::parse_palette)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteLut {
+    name: String,
+    colors: Vec<[u8; 3]>,
+}
+
+impl PaletteLut {
+    pub fn new(name: impl Into<String>, colors: Vec<[u8; 3]>) -> Result<Self, FlirError> {
+        if colors.is_empty() {
+            return Err(FlirError::InvalidFormat("palette has no colors".into()));
+        }
+        Ok(PaletteLut {
+            name: name.into(),
+            colors,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn colors(&self) -> &[[u8; 3]] {
+        &self.colors
+    }
+
+    /// Maps `t` in `0.0..=1.0` to the nearest entry. Values outside the range are clamped.
+    pub fn color(&self, t: f32) -> [u8; 3] {
+        let last = self.colors.len() - 1;
+        self.colors[(t.clamp(0.0, 1.0) * last as f32).round() as usize]
+    }
+}
+
+/// Interpolates between the two stops either side of `t`, which must be in `0.0..=1.0`.
+fn gradient(stops: &[[f32; 3]], t: f32) -> [u8; 3] {
+    let position = t * (stops.len() - 1) as f32;
+    let low = (position.floor() as usize).min(stops.len() - 2);
+    lerp(stops[low], stops[low + 1], position - low as f32)
+}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [u8; 3] {
+    [0, 1, 2].map(|i| (a[i] + (b[i] - a[i]) * t).round() as u8)
+}
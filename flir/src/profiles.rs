@@ -0,0 +1,111 @@
+//! Named [`ObjectParameters`] presets for common target materials.
+//!
+//! Profiles can be loaded from TOML, one table per profile. Fields left out take their
+//! [`ObjectParameters::default`] value, and every profile has to pass
+//! [`ObjectParameters::check`]:
+//!
+//! ```toml
+//! ["human skin"]
+//! emissivity = 0.98
+//!
+//! ["oven door"]
+//! emissivity = 0.92
+//! distance = 0.5
+//! ```
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{radiometry::ObjectParameters, FlirError};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profiles {
+    profiles: BTreeMap<String, ObjectParameters>,
+}
+
+impl Profiles {
+    pub fn new() -> Self {
+        Profiles::default()
+    }
+
+    /// A few everyday materials, with textbook emissivities.
+    pub fn builtin() -> Self {
+        let profiles = [
+            ("human skin", 0.98),
+            ("painted metal", 0.90),
+            ("concrete", 0.92),
+        ]
+        .into_iter()
+        .map(|(name, emissivity)| {
+            let params = ObjectParameters {
+                emissivity,
+                ..ObjectParameters::default()
+            };
+            (name.to_owned(), params)
+        })
+        .collect();
+        Profiles { profiles }
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, FlirError> {
+        let profiles: BTreeMap<String, ObjectParameters> =
+            toml::from_str(toml).map_err(|e| FlirError::InvalidFormat(e.to_string()))?;
+        for (name, params) in &profiles {
+            if let Err(FlirError::InvalidFormat(why)) = params.check() {
+                return Err(FlirError::InvalidFormat(format!("profile {name:?}: {why}")));
+            }
+        }
+        Ok(Profiles { profiles })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FlirError> {
+        Profiles::from_toml(&fs::read_to_string(path)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ObjectParameters> {
+        self.profiles.get(name)
+    }
+
+    /// Adds or replaces a profile. Fails, leaving the profiles as they were, if `params`
+    /// does not pass [`ObjectParameters::check`].
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        params: ObjectParameters,
+    ) -> Result<(), FlirError> {
+        params.check()?;
+        self.profiles.insert(name.into(), params);
+        Ok(())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Calibration;
+
+    #[test]
+    fn rejects_emissivities_the_conversion_cannot_divide_by() {
+        for emissivity in ["0", "-0.5", "1.5", "nan"] {
+            let toml = format!("[bad]\nemissivity = {emissivity}\n");
+            assert!(matches!(
+                Profiles::from_toml(&toml),
+                Err(FlirError::InvalidFormat(_))
+            ));
+        }
+        let skin = Profiles::from_toml("[skin]\nemissivity = 0.98\n").unwrap();
+        assert_eq!(skin.get("skin").unwrap().emissivity, 0.98);
+
+        let zero = ObjectParameters {
+            emissivity: 0.0,
+            ..ObjectParameters::default()
+        };
+        let mut profiles = Profiles::builtin();
+        assert!(profiles.insert("vacuum", zero).is_err());
+        assert!(profiles.get("vacuum").is_none());
+        assert!(Calibration::default_flir_one().with_object(zero).is_err());
+    }
+}
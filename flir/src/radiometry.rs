@@ -0,0 +1,319 @@
+use std::{fs, io, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::FlirError;
+
+/// Scene parameters that affect the conversion of raw counts to temperature.
+/// Temperatures are in degrees Celsius, humidity is a fraction in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObjectParameters {
+    pub emissivity: f32,
+    pub distance: f32,
+    pub reflected_temperature: f32,
+    pub atmospheric_temperature: f32,
+    pub ir_window_temperature: f32,
+    pub ir_window_transmission: f32,
+    pub relative_humidity: f32,
+}
+
+impl Default for ObjectParameters {
+    /// A target one metre away in a 20 °C room at 50% humidity.
+    fn default() -> Self {
+        ObjectParameters {
+            emissivity: 0.95,
+            distance: 1.0,
+            reflected_temperature: 20.0,
+            atmospheric_temperature: 20.0,
+            ir_window_temperature: 20.0,
+            ir_window_transmission: 1.0,
+            relative_humidity: 0.5,
+        }
+    }
+}
+
+impl ObjectParameters {
+    /// Rejects an emissivity outside `0 < e <= 1`, which the conversion divides by.
+    pub fn check(&self) -> Result<(), FlirError> {
+        check_emissivity(self.emissivity)
+    }
+}
+
+pub(crate) fn check_emissivity(emissivity: f32) -> Result<(), FlirError> {
+    if emissivity > 0.0 && emissivity <= 1.0 {
+        Ok(())
+    } else {
+        Err(FlirError::InvalidFormat(format!(
+            "emissivity {emissivity} is outside 0..=1"
+        )))
+    }
+}
+
+/// Coefficients of the atmospheric transmission model.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AtmosphericConstants {
+    pub alpha1: f32,
+    pub alpha2: f32,
+    pub beta1: f32,
+    pub beta2: f32,
+    pub x: f32,
+}
+
+/// Per-unit Planck constants, plus the scene parameters they were stored with.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub planck_r1: f32,
+    pub planck_r2: f32,
+    pub planck_b: f32,
+    pub planck_f: f32,
+    pub planck_o: f32,
+    pub atmosphere: AtmosphericConstants,
+    pub object: ObjectParameters,
+}
+
+const KELVIN_OFFSET: f64 = 273.15;
+
+/// Unit temperatures are reported in. Conversions are done in Celsius and converted last.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TempUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TempUnit {
+    pub fn from_celsius(self, celsius: f32) -> f32 {
+        match self {
+            TempUnit::Celsius => celsius,
+            TempUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            TempUnit::Kelvin => celsius + KELVIN_OFFSET as f32,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "°C",
+            TempUnit::Fahrenheit => "°F",
+            TempUnit::Kelvin => "K",
+        }
+    }
+
+    /// `celsius` in this unit with one decimal, e.g. `98.6 °F`, for labels.
+    pub fn format(self, celsius: f32) -> String {
+        format!("{:.1} {}", self.from_celsius(celsius), self.symbol())
+    }
+}
+
+impl FromStr for TempUnit {
+    type Err = FlirError;
+
+    /// Parses `celsius`, `fahrenheit` or `kelvin`, or their first letter.
+    fn from_str(name: &str) -> Result<Self, FlirError> {
+        match name.to_ascii_lowercase().as_str() {
+            "c" | "celsius" => Ok(TempUnit::Celsius),
+            "f" | "fahrenheit" => Ok(TempUnit::Fahrenheit),
+            "k" | "kelvin" => Ok(TempUnit::Kelvin),
+            _ => Err(FlirError::InvalidFormat(format!(
+                "unknown temperature unit {name:?}"
+            ))),
+        }
+    }
+}
+
+impl Calibration {
+    /// Writes the calibration as JSON, to be read back with [`Calibration::load`].
+    pub fn save(&self, path: &Path) -> Result<(), FlirError> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Calibration, FlirError> {
+        let bytes = fs::read(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                FlirError::NoCalibration(format!("{} does not exist", path.display()))
+            }
+            _ => FlirError::Io(e),
+        })?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Constants typical of a FLIR One with default scene parameters. They are not this
+    /// unit's own, so temperatures can be off by several degrees; only for when
+    /// [`FlirOne::read_calibration`](crate::FlirOne::read_calibration) is not an option.
+    pub fn default_flir_one() -> Calibration {
+        Calibration {
+            planck_r1: 16528.178,
+            planck_r2: 0.012258549,
+            planck_b: 1427.5,
+            planck_f: 1.0,
+            planck_o: -1307.0,
+            atmosphere: AtmosphericConstants {
+                alpha1: 0.006569,
+                alpha2: 0.01262,
+                beta1: -0.002276,
+                beta2: -0.00667,
+                x: 1.9,
+            },
+            object: ObjectParameters::default(),
+        }
+    }
+
+    /// The same calibration applied to a different scene, e.g. one from
+    /// [`Profiles`](crate::profiles::Profiles). Fails if `object` does not pass
+    /// [`ObjectParameters::check`].
+    pub fn with_object(&self, object: ObjectParameters) -> Result<Calibration, FlirError> {
+        object.check()?;
+        Ok(Calibration { object, ..*self })
+    }
+
+    /// Converts a raw sensor count to degrees Celsius for the scene described by `object`.
+    pub fn raw_to_celsius(&self, raw: u16) -> f32 {
+        self.conversion().celsius(raw)
+    }
+
+    /// Radiance of the object alone, with the reflected, atmospheric and window
+    /// contributions removed. It is in the camera's own units, raw counts on the scale of
+    /// the Planck fit, not W/(m²·sr): [`Calibration::raw_to_celsius`] is this value put
+    /// through the inverse Planck curve `B / ln(R1 / (R2 · (radiance + O)) + F)`.
+    pub fn raw_to_radiance(&self, raw: u16) -> f32 {
+        self.conversion().radiance(raw as f64) as f32
+    }
+
+    pub fn raw_to_temperature(&self, raw: u16, unit: TempUnit) -> f32 {
+        unit.from_celsius(self.raw_to_celsius(raw))
+    }
+
+    pub(crate) fn conversion(&self) -> Conversion {
+        let o = &self.object;
+        let a = &self.atmosphere;
+        let emissivity = o.emissivity as f64;
+        let window = if o.ir_window_transmission > 0.0 {
+            o.ir_window_transmission as f64
+        } else {
+            1.0
+        };
+
+        let t_atm = o.atmospheric_temperature as f64;
+        let h2o = o.relative_humidity as f64
+            * (1.5587 + 0.06939 * t_atm - 0.00027816 * t_atm.powi(2)
+                + 0.00000068455 * t_atm.powi(3))
+            .exp();
+        let path = -(o.distance as f64 / 2.0).sqrt();
+        let tau = a.x as f64 * (path * (a.alpha1 as f64 + a.beta1 as f64 * h2o.sqrt())).exp()
+            + (1.0 - a.x as f64) * (path * (a.alpha2 as f64 + a.beta2 as f64 * h2o.sqrt())).exp();
+
+        let reflected = (1.0 - emissivity) / emissivity * self.planck(o.reflected_temperature);
+        let atm = self.planck(o.atmospheric_temperature);
+        let atm_near = (1.0 - tau) / emissivity / tau * atm;
+        let atm_far = (1.0 - tau) / emissivity / tau / window / tau * atm;
+        let wind =
+            (1.0 - window) / emissivity / tau / window * self.planck(o.ir_window_temperature);
+
+        Conversion {
+            r1: self.planck_r1 as f64,
+            r2: self.planck_r2 as f64,
+            b: self.planck_b as f64,
+            f: self.planck_f as f64,
+            o: self.planck_o as f64,
+            scale: 1.0 / (emissivity * tau * window * tau),
+            offset: reflected + atm_near + atm_far + wind,
+        }
+    }
+
+    /// Raw count a blackbody at `celsius` would produce.
+    fn planck(&self, celsius: f32) -> f64 {
+        self.planck_r1 as f64
+            / (self.planck_r2 as f64
+                * ((self.planck_b as f64 / (celsius as f64 + KELVIN_OFFSET)).exp()
+                    - self.planck_f as f64))
+            - self.planck_o as f64
+    }
+}
+
+/// Temperatures of a whole thermal plane, in degrees Celsius and row-major, as produced by
+/// [`Frame::temperatures`](crate::Frame::temperatures).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemperatureMap {
+    width: u32,
+    height: u32,
+    celsius: Vec<f32>,
+}
+
+impl TemperatureMap {
+    pub(crate) fn new(width: u32, height: u32, celsius: Vec<f32>) -> Self {
+        TemperatureMap {
+            width,
+            height,
+            celsius,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn celsius(&self) -> &[f32] {
+        &self.celsius
+    }
+
+    /// Temperature of the pixel at `(x, y)` in degrees Celsius.
+    pub fn get(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.celsius[(y * self.width + x) as usize])
+    }
+
+    /// Every temperature in `unit`, in the same order as [`TemperatureMap::celsius`].
+    pub fn to_unit(&self, unit: TempUnit) -> Vec<f32> {
+        self.celsius.iter().map(|&c| unit.from_celsius(c)).collect()
+    }
+
+    pub fn kelvin(&self) -> Vec<f32> {
+        self.to_unit(TempUnit::Kelvin)
+    }
+
+    /// Lowest and highest temperature, or `None` for an empty map.
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.celsius.iter().fold(None, |range, &c| match range {
+            None => Some((c, c)),
+            Some((lo, hi)) => Some((lo.min(c), hi.max(c))),
+        })
+    }
+}
+
+/// [`Calibration::raw_to_celsius`] with the scene-dependent terms worked out once, for
+/// converting whole images.
+pub(crate) struct Conversion {
+    r1: f64,
+    r2: f64,
+    b: f64,
+    f: f64,
+    o: f64,
+    scale: f64,
+    offset: f64,
+}
+
+impl Conversion {
+    pub fn celsius(&self, raw: u16) -> f32 {
+        self.celsius_interpolated(raw as f64)
+    }
+
+    /// [`Conversion::celsius`] for a raw count between two integer ones.
+    pub fn celsius_interpolated(&self, raw: f64) -> f32 {
+        let object = self.radiance(raw);
+        (self.b / (self.r1 / (self.r2 * (object + self.o)) + self.f).ln() - KELVIN_OFFSET) as f32
+    }
+
+    /// See [`Calibration::raw_to_radiance`].
+    pub fn radiance(&self, raw: f64) -> f64 {
+        raw * self.scale - self.offset
+    }
+}
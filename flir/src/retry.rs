@@ -0,0 +1,79 @@
+use std::{thread, time::Duration};
+
+use crate::FlirError;
+
+/// How often to repeat a USB transfer that failed with a transient error (`Timeout`,
+/// `Pipe` or `Busy`). Other errors are returned straight away.
+///
+/// Each attempt gets the transfer's full timeout, so a read that keeps timing out takes
+/// up to `max_attempts` times as long, plus the backoff. Reads whose timeout is only a
+/// poll, such as flushing the endpoints or cancellable and watchdog frame reads, do not
+/// retry `Timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first. `1` disables retrying.
+    pub max_attempts: u32,
+    /// Pause before each retry.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub const NONE: RetryPolicy = RetryPolicy {
+        max_attempts: 1,
+        backoff: Duration::ZERO,
+    };
+
+    pub fn new(max_attempts: u32, backoff: Duration) -> Self {
+        RetryPolicy {
+            max_attempts,
+            backoff,
+        }
+    }
+
+    pub(crate) fn run<R>(
+        &self,
+        transfer: impl FnMut() -> Result<R, FlirError>,
+    ) -> Result<R, FlirError> {
+        self.run_if(transfer, is_transient)
+    }
+
+    /// [`RetryPolicy::run`] for a poll, where timing out is an answer rather than a failure.
+    pub(crate) fn run_polling<R>(
+        &self,
+        transfer: impl FnMut() -> Result<R, FlirError>,
+    ) -> Result<R, FlirError> {
+        self.run_if(transfer, |e| {
+            is_transient(e) && !matches!(e, FlirError::Usb(rusb::Error::Timeout))
+        })
+    }
+
+    fn run_if<R>(
+        &self,
+        mut transfer: impl FnMut() -> Result<R, FlirError>,
+        retry: fn(&FlirError) -> bool,
+    ) -> Result<R, FlirError> {
+        let mut attempt = 1;
+        loop {
+            match transfer() {
+                Err(e) if attempt < self.max_attempts && retry(&e) => {
+                    attempt += 1;
+                    thread::sleep(self.backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::NONE
+    }
+}
+
+fn is_transient(e: &FlirError) -> bool {
+    matches!(
+        e,
+        FlirError::Usb(rusb::Error::Timeout | rusb::Error::Pipe | rusb::Error::Busy)
+    )
+}
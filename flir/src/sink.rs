@@ -0,0 +1,273 @@
+//! Destinations for captured frames, driven by [`FlirOne::run_stream`](crate::FlirOne::run_stream).
+
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{
+    frame::FrameParser, Calibration, FlirError, Frame, FrameStats, MotionDetector, Palette, Rect,
+    ThermalImage,
+};
+
+/// First byte of a zlib stream with the usual 32 KiB window.
+const ZLIB_CMF: u8 = 0x78;
+const RAW_READ_CHUNK: usize = 65536;
+
+pub trait FrameSink {
+    fn consume(&mut self, frame: &Frame) -> Result<(), FlirError>;
+}
+
+impl<S: FrameSink + ?Sized> FrameSink for &mut S {
+    fn consume(&mut self, frame: &Frame) -> Result<(), FlirError> {
+        (**self).consume(frame)
+    }
+}
+
+impl<S: FrameSink + ?Sized> FrameSink for Box<S> {
+    fn consume(&mut self, frame: &Frame) -> Result<(), FlirError> {
+        (**self).consume(frame)
+    }
+}
+
+/// Discards every frame.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullSink;
+
+impl FrameSink for NullSink {
+    fn consume(&mut self, _frame: &Frame) -> Result<(), FlirError> {
+        Ok(())
+    }
+}
+
+/// Writes each frame's thermal image as `frame_000000.png`, `frame_000001.png`, ... in a
+/// directory.
+#[derive(Debug, Clone)]
+pub struct PngSequenceSink {
+    dir: PathBuf,
+    palette: Palette,
+    next: u64,
+    sidecar: Option<Sidecar>,
+}
+
+#[derive(Debug, Clone)]
+struct Sidecar {
+    calibration: Calibration,
+    gain_mode: Option<String>,
+}
+
+impl PngSequenceSink {
+    /// Creates `dir` if needed.
+    pub fn new(dir: impl Into<PathBuf>, palette: Palette) -> Result<Self, FlirError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(PngSequenceSink {
+            dir,
+            palette,
+            next: 0,
+            sidecar: None,
+        })
+    }
+
+    /// Also writes each frame's [`FrameStats`] as `frame_000000.json` next to its image.
+    pub fn with_stats(mut self, calibration: Calibration, gain_mode: Option<String>) -> Self {
+        self.sidecar = Some(Sidecar {
+            calibration,
+            gain_mode,
+        });
+        self
+    }
+}
+
+impl FrameSink for PngSequenceSink {
+    fn consume(&mut self, frame: &Frame) -> Result<(), FlirError> {
+        let png = ThermalImage::from_frame(frame)?.to_png(self.palette)?;
+        fs::write(self.dir.join(format!("frame_{:06}.png", self.next)), png)?;
+        if let Some(sidecar) = &self.sidecar {
+            let stats =
+                FrameStats::compute(frame, &sidecar.calibration, sidecar.gain_mode.as_deref())?;
+            fs::write(
+                self.dir.join(format!("frame_{:06}.json", self.next)),
+                stats.to_json()?,
+            )?;
+        }
+        self.next += 1;
+        Ok(())
+    }
+}
+
+/// Appends frames, as [`Frame::to_bytes`], to one file that a
+/// [`FrameParser`] can split again, or that [`RawFileReader`]
+/// reads back whether or not it is compressed.
+#[derive(Debug)]
+pub struct RawFileSink {
+    file: RawWriter,
+}
+
+#[derive(Debug)]
+enum RawWriter {
+    Plain(BufWriter<File>),
+    Zlib(ZlibEncoder<BufWriter<File>>),
+}
+
+impl RawFileSink {
+    pub fn create(path: &Path) -> Result<Self, FlirError> {
+        Ok(RawFileSink {
+            file: RawWriter::Plain(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// Like [`RawFileSink::create`], but zlib-compresses the file at `level`, from `0`
+    /// (fastest) to `9` (smallest). The thermal plane varies slowly from pixel to pixel,
+    /// so recordings typically shrink to well under half their size without losing
+    /// anything. Each frame is flushed whole, so a file cut short still reads back up to
+    /// its last frame.
+    pub fn create_compressed(path: &Path, level: u32) -> Result<Self, FlirError> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(RawFileSink {
+            file: RawWriter::Zlib(ZlibEncoder::new(file, Compression::new(level.min(9)))),
+        })
+    }
+
+    /// Writes the end of the compressed stream. Dropping the sink does the same but
+    /// cannot report errors.
+    pub fn finish(self) -> Result<(), FlirError> {
+        match self.file {
+            RawWriter::Plain(mut file) => file.flush()?,
+            RawWriter::Zlib(encoder) => encoder.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+impl FrameSink for RawFileSink {
+    fn consume(&mut self, frame: &Frame) -> Result<(), FlirError> {
+        let bytes = frame.to_bytes();
+        match &mut self.file {
+            RawWriter::Plain(file) => {
+                file.write_all(&bytes)?;
+                file.flush()?;
+            }
+            RawWriter::Zlib(encoder) => {
+                encoder.write_all(&bytes)?;
+                encoder.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads back the frames of a [`RawFileSink`] file, compressed or not.
+pub struct RawFileReader {
+    file: Box<dyn Read>,
+    parser: FrameParser,
+    done: bool,
+}
+
+impl RawFileReader {
+    pub fn open(path: &Path) -> Result<Self, FlirError> {
+        let mut file = BufReader::new(File::open(path)?);
+        // Raw files start with a frame magic, compressed ones with a zlib header.
+        let compressed = file.fill_buf()?.first() == Some(&ZLIB_CMF);
+        Ok(RawFileReader {
+            file: if compressed {
+                Box::new(ZlibDecoder::new(file))
+            } else {
+                Box::new(file)
+            },
+            parser: FrameParser::new(),
+            done: false,
+        })
+    }
+}
+
+impl Iterator for RawFileReader {
+    type Item = Result<Frame, FlirError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = vec![0u8; RAW_READ_CHUNK];
+        loop {
+            match self.parser.next_frame() {
+                Ok(Some(frame)) => return Some(Ok(frame)),
+                Ok(None) if self.done => return None,
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            match self.file.read(&mut buf) {
+                Ok(0) => self.done = true,
+                Ok(n) => self.parser.push(&buf[..n]),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+}
+
+/// Hands every frame to each of its sinks in turn, stopping at the first error.
+#[derive(Default)]
+pub struct TeeSink {
+    sinks: Vec<Box<dyn FrameSink>>,
+}
+
+impl TeeSink {
+    pub fn new() -> Self {
+        TeeSink::default()
+    }
+
+    pub fn with(mut self, sink: impl FrameSink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+}
+
+impl FrameSink for TeeSink {
+    fn consume(&mut self, frame: &Frame) -> Result<(), FlirError> {
+        for sink in &mut self.sinks {
+            sink.consume(frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// Forwards only the frames a [`MotionDetector`] fires on.
+#[derive(Debug)]
+pub struct MotionSink<S> {
+    detector: MotionDetector,
+    sink: S,
+    last_motion: Option<Rect>,
+}
+
+impl<S: FrameSink> MotionSink<S> {
+    pub fn new(detector: MotionDetector, sink: S) -> Self {
+        MotionSink {
+            detector,
+            sink,
+            last_motion: None,
+        }
+    }
+
+    /// Where the last forwarded frame changed.
+    pub fn last_motion(&self) -> Option<Rect> {
+        self.last_motion
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+}
+
+impl<S: FrameSink> FrameSink for MotionSink<S> {
+    fn consume(&mut self, frame: &Frame) -> Result<(), FlirError> {
+        let image = ThermalImage::from_frame(frame)?;
+        let Some(motion) = self.detector.update(&image) else {
+            return Ok(());
+        };
+        self.last_motion = Some(motion);
+        self.sink.consume(frame)
+    }
+}
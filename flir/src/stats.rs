@@ -0,0 +1,149 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::{radiometry::TempUnit, Calibration, FlirError, Frame, ThermalImage};
+
+/// A temperature reading at a pixel, and when the frame it came from arrived.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Extreme {
+    pub celsius: f32,
+    pub x: u32,
+    pub y: u32,
+    pub at: SystemTime,
+}
+
+impl Extreme {
+    pub fn temperature(&self, unit: TempUnit) -> f32 {
+        unit.from_celsius(self.celsius)
+    }
+}
+
+/// All-time minimum and maximum temperatures over a capture, kept without storing the
+/// frames themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SessionStats {
+    frames: u64,
+    min: Option<Extreme>,
+    max: Option<Extreme>,
+    restarts: u64,
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        SessionStats::default()
+    }
+
+    /// Folds in one frame. Only the coldest and hottest raw counts are converted, as the
+    /// conversion preserves their order.
+    pub fn update(&mut self, image: &ThermalImage, cal: &Calibration, at: SystemTime) {
+        let width = image.width() as usize;
+        let pixels = image.pixels().iter().copied().enumerate();
+        let (Some(coldest), Some(hottest)) = (
+            pixels.clone().min_by_key(|&(_, raw)| raw),
+            pixels.max_by_key(|&(_, raw)| raw),
+        ) else {
+            return;
+        };
+        let extreme = |(i, raw): (usize, u16)| Extreme {
+            celsius: cal.raw_to_celsius(raw),
+            x: (i % width) as u32,
+            y: (i / width) as u32,
+            at,
+        };
+
+        let coldest = extreme(coldest);
+        if self.min.is_none_or(|min| coldest.celsius < min.celsius) {
+            self.min = Some(coldest);
+        }
+        let hottest = extreme(hottest);
+        if self.max.is_none_or(|max| hottest.celsius > max.celsius) {
+            self.max = Some(hottest);
+        }
+        self.frames += 1;
+    }
+
+    pub fn reset(&mut self) {
+        *self = SessionStats::default();
+    }
+
+    /// Number of frames folded in since the last reset.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Times a [`FrameStream`](crate::FrameStream) watchdog restarted the frame protocol.
+    pub fn restarts(&self) -> u64 {
+        self.restarts
+    }
+
+    pub(crate) fn record_restart(&mut self) {
+        self.restarts += 1;
+    }
+
+    pub fn min(&self) -> Option<Extreme> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<Extreme> {
+        self.max
+    }
+}
+
+/// Temperature statistics of one frame, written as a JSON sidecar next to saved frames
+/// so captures can be indexed without decoding them again.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrameStats {
+    pub min_celsius: f32,
+    pub max_celsius: f32,
+    pub mean_celsius: f32,
+    /// Pixel of the maximum.
+    pub hotspot: (u32, u32),
+    /// Milliseconds since the Unix epoch when the stats were taken.
+    pub received_at: u64,
+    /// [`Frame::timestamp`] in seconds, if the camera sent one.
+    pub camera_timestamp: Option<f64>,
+    pub gain_mode: Option<String>,
+}
+
+impl FrameStats {
+    pub fn compute(
+        frame: &Frame,
+        cal: &Calibration,
+        gain_mode: Option<&str>,
+    ) -> Result<Self, FlirError> {
+        let image = ThermalImage::from_frame(frame)?;
+        let temps = image.to_celsius(cal);
+        let width = image.width() as usize;
+        let (hottest, max) =
+            temps
+                .iter()
+                .copied()
+                .enumerate()
+                .fold((0, f32::NEG_INFINITY), |best, (i, t)| {
+                    if t > best.1 {
+                        (i, t)
+                    } else {
+                        best
+                    }
+                });
+        let min = temps.iter().copied().fold(f32::INFINITY, f32::min);
+        let mean = temps.iter().sum::<f32>() / temps.len().max(1) as f32;
+        Ok(FrameStats {
+            min_celsius: min,
+            max_celsius: max,
+            mean_celsius: mean,
+            hotspot: ((hottest % width) as u32, (hottest / width) as u32),
+            received_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            camera_timestamp: frame.timestamp().map(|t| t.as_secs_f64()),
+            gain_mode: gain_mode.map(str::to_owned),
+        })
+    }
+
+    /// The stats as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, FlirError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
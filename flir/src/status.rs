@@ -0,0 +1,36 @@
+use serde_json::Value;
+
+/// A status message from the CONFIG endpoint, shaped `{"type": ..., "data": {...}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraStatus {
+    pub kind: String,
+    /// `data.ready` when the camera sends it, otherwise whether it reported no error.
+    pub ready: bool,
+    pub error_code: Option<i64>,
+    pub data: Value,
+}
+
+impl CameraStatus {
+    pub(crate) fn from_json(json: Value) -> Self {
+        let kind = json
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned();
+        let data = json.get("data").cloned().unwrap_or(Value::Null);
+        let error_code = json
+            .get("error")
+            .or_else(|| data.get("error"))
+            .and_then(Value::as_i64);
+        let ready = data
+            .get("ready")
+            .and_then(Value::as_bool)
+            .unwrap_or(error_code.is_none());
+        CameraStatus {
+            kind,
+            ready,
+            error_code,
+            data,
+        }
+    }
+}
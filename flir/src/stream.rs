@@ -0,0 +1,395 @@
+use std::{
+    collections::VecDeque,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    config::ShutterAction, Calibration, FlirError, FlirOne, Frame, SessionStats, ThermalImage,
+    Transport,
+};
+
+/// Inter-frame intervals [`FrameStream::measured_fps`] averages over unless
+/// [`FrameStream::with_fps_window`] says otherwise.
+const DEFAULT_FPS_WINDOW: usize = 30;
+
+type Validator<'d> = Box<dyn Fn(&Frame) -> bool + 'd>;
+
+/// Frames read one after another from a [`FlirOne`], see [`FlirOne::frames`].
+pub struct FrameStream<'d, T: Transport> {
+    device: &'d mut FlirOne<T>,
+    calibration: Option<Calibration>,
+    stats: SessionStats,
+    arrivals: VecDeque<Instant>,
+    fps_window: usize,
+    discard_shutter_frames: bool,
+    warmup: usize,
+    start_lazily: bool,
+    watchdog: Option<Duration>,
+    watchdog_nuc: bool,
+    last_restart: Option<Instant>,
+    validator: Option<Validator<'d>>,
+    rejected: u64,
+    last_counter: Option<u32>,
+    dropped: u64,
+}
+
+impl<'d, T: Transport> FrameStream<'d, T> {
+    pub(crate) fn new(device: &'d mut FlirOne<T>) -> Self {
+        FrameStream {
+            calibration: device.calibration().copied(),
+            device,
+            stats: SessionStats::new(),
+            arrivals: VecDeque::new(),
+            fps_window: DEFAULT_FPS_WINDOW,
+            discard_shutter_frames: false,
+            warmup: 0,
+            start_lazily: false,
+            watchdog: None,
+            watchdog_nuc: false,
+            last_restart: None,
+            validator: None,
+            rejected: 0,
+            last_counter: None,
+            dropped: 0,
+        }
+    }
+
+    pub(crate) fn start_lazily(mut self) -> Self {
+        self.start_lazily = true;
+        self
+    }
+
+    /// Converts each frame with `calibration` to keep [`FrameStream::session_stats`] up to
+    /// date. Defaults to the device's [`FlirOne::calibration`]; without either the
+    /// statistics stay empty.
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    /// Reads and drops the first `n` frames, which are often unusable while the sensor
+    /// settles.
+    pub fn with_warmup(mut self, n: usize) -> Self {
+        self.warmup = n;
+        self
+    }
+
+    /// Stops and restarts the frame protocol whenever the camera sends nothing for `idle`,
+    /// as some units silently stop streaming after a while. Restarts are counted in
+    /// [`SessionStats::restarts`], and the latest is given by [`FrameStream::last_restart`].
+    pub fn with_watchdog(mut self, idle: Duration) -> Self {
+        self.watchdog = Some(idle);
+        self
+    }
+
+    /// Has each [`FrameStream::with_watchdog`] restart also run a NUC, a shutter
+    /// recalibration with [`ShutterAction::Calibrate`], once frames are started again.
+    pub fn with_watchdog_nuc(mut self) -> Self {
+        self.watchdog_nuc = true;
+        self
+    }
+
+    /// Skips frames taken with the shutter closed, see [`Frame::is_shutter_frame`].
+    pub fn discard_shutter_frames(mut self) -> Self {
+        self.discard_shutter_frames = true;
+        self
+    }
+
+    /// Drops frames for which `validator` returns `false`, e.g. ones with implausible
+    /// statistics on quirky firmware. Frames are checked whole after reassembly, so
+    /// dropping one does not disturb the next; [`FrameStream::rejected_count`] counts them.
+    pub fn with_validator(mut self, validator: impl Fn(&Frame) -> bool + 'd) -> Self {
+        self.validator = Some(Box::new(validator));
+        self
+    }
+
+    /// Frames the camera sent that never arrived, from gaps in [`Frame::counter`]. The
+    /// counter wraps, which is not counted as a gap; nor is it going backwards, e.g. when
+    /// the camera restarts.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped
+    }
+
+    /// When [`FrameStream::with_watchdog`] last restarted the frame protocol.
+    pub fn last_restart(&self) -> Option<Instant> {
+        self.last_restart
+    }
+
+    /// Frames dropped by [`FrameStream::with_validator`] so far.
+    pub fn rejected_count(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Yields frames until `deadline`, then stops the frame protocol. A read in progress
+    /// at the deadline is allowed to finish.
+    pub fn take_until(self, deadline: Instant) -> BoundedStream<'d, T> {
+        BoundedStream::new(self, Limit::Deadline(deadline))
+    }
+
+    /// Yields `n` frames, then stops the frame protocol.
+    pub fn take_count(self, n: usize) -> BoundedStream<'d, T> {
+        BoundedStream::new(self, Limit::Count(n))
+    }
+
+    /// Averages [`FrameStream::measured_fps`] over the last `frames` inter-frame intervals.
+    pub fn with_fps_window(mut self, frames: usize) -> Self {
+        self.fps_window = frames.max(1);
+        self
+    }
+
+    /// Frame rate over the recent frames, from the time each one arrived. `0.0` until two
+    /// frames have been read.
+    pub fn measured_fps(&self) -> f32 {
+        let (Some(first), Some(last)) = (self.arrivals.front(), self.arrivals.back()) else {
+            return 0.0;
+        };
+        let elapsed = last.duration_since(*first).as_secs_f32();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        (self.arrivals.len() - 1) as f32 / elapsed
+    }
+
+    /// A snapshot of the statistics over the frames read so far.
+    pub fn session_stats(&self) -> SessionStats {
+        self.stats
+    }
+
+    pub fn reset_session_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    pub fn device(&mut self) -> &mut FlirOne<T> {
+        self.device
+    }
+
+    fn read_frame(&mut self) -> Result<Frame, FlirError> {
+        let Some(idle) = self.watchdog else {
+            return self.device.read_frame();
+        };
+        loop {
+            match self.device.read_frame_timeout(idle) {
+                Err(FlirError::Timeout { .. } | FlirError::NoData) => {
+                    self.device.stop_stream()?;
+                    self.device.flush_frame_endpoint()?;
+                    self.device.start_stream()?;
+                    if self.watchdog_nuc {
+                        self.device
+                            .config_channel()?
+                            .shutter_control(ShutterAction::Calibrate)?;
+                    }
+                    self.stats.record_restart();
+                    self.last_restart = Some(Instant::now());
+                    self.last_counter = None;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Yields frames no faster than their camera timestamps say they were taken, see
+    /// [`PacedStream`].
+    pub fn paced(self) -> PacedStream<Self> {
+        PacedStream::new(self)
+    }
+}
+
+impl<T: Transport> Iterator for FrameStream<'_, T> {
+    type Item = Result<Frame, FlirError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start_lazily {
+            self.start_lazily = false;
+            if let Err(e) = self.device.ensure_streaming() {
+                return Some(Err(e));
+            }
+        }
+        while self.warmup > 0 {
+            if let Err(e) = self.read_frame() {
+                return Some(Err(e));
+            }
+            self.warmup -= 1;
+        }
+        let frame = loop {
+            let frame = self.read_frame();
+            if let Ok(counter) = frame.as_ref().map(Frame::counter) {
+                if let (Some(previous), Some(counter)) = (self.last_counter, counter) {
+                    self.dropped += u64::from(frames_missed(previous, counter));
+                }
+                self.last_counter = counter.or(self.last_counter);
+            }
+            match frame {
+                Ok(frame) if self.discard_shutter_frames && frame.is_shutter_frame() => {}
+                Ok(frame) if self.validator.as_ref().is_some_and(|valid| !valid(&frame)) => {
+                    self.rejected += 1;
+                }
+                Ok(frame) => break frame,
+                Err(e) => return Some(Err(e)),
+            }
+        };
+        self.arrivals.push_back(Instant::now());
+        while self.arrivals.len() > self.fps_window + 1 {
+            self.arrivals.pop_front();
+        }
+        // Statistics are a side job: a frame whose thermal plane does not decode is still
+        // delivered, as it would be without a calibration, and simply left out of them.
+        if let Some(cal) = &self.calibration {
+            if let Ok(image) = ThermalImage::from_frame(&frame) {
+                self.stats.update(&image, cal, SystemTime::now());
+            }
+        }
+        Some(Ok(frame))
+    }
+}
+
+/// Frames between two counter values, going forwards with wrapping arithmetic so a
+/// rollover is not taken for four billion lost frames. A step backwards, which wraps to
+/// more than half the counter's range, means the counter was reset and counts as none.
+fn frames_missed(previous: u32, counter: u32) -> u32 {
+    let step = counter.wrapping_sub(previous);
+    if step == 0 || step > u32::MAX / 2 {
+        0
+    } else {
+        step - 1
+    }
+}
+
+enum Limit {
+    Deadline(Instant),
+    Count(usize),
+}
+
+/// A [`FrameStream`] that ends by itself, see [`FrameStream::take_until`] and
+/// [`FrameStream::take_count`]. The frame protocol is stopped when it ends or is dropped.
+pub struct BoundedStream<'d, T: Transport> {
+    stream: FrameStream<'d, T>,
+    limit: Limit,
+    taken: usize,
+    finished: bool,
+}
+
+impl<'d, T: Transport> BoundedStream<'d, T> {
+    fn new(stream: FrameStream<'d, T>, limit: Limit) -> Self {
+        BoundedStream {
+            stream,
+            limit,
+            taken: 0,
+            finished: false,
+        }
+    }
+
+    /// The stream being bounded, e.g. for its statistics.
+    pub fn stream(&self) -> &FrameStream<'d, T> {
+        &self.stream
+    }
+
+    fn finish(&mut self) -> Result<(), FlirError> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        self.stream.device.pause()
+    }
+}
+
+impl<T: Transport> Iterator for BoundedStream<'_, T> {
+    type Item = Result<Frame, FlirError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let done = match self.limit {
+            Limit::Deadline(deadline) => Instant::now() >= deadline,
+            Limit::Count(n) => self.taken >= n,
+        };
+        if done {
+            return self.finish().err().map(Err);
+        }
+        self.taken += 1;
+        self.stream.next()
+    }
+}
+
+impl<T: Transport> Drop for BoundedStream<'_, T> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// Replays frames at the rate they were captured, e.g. a recording queued on a
+/// [`MockTransport`](crate::MockTransport), by sleeping until each frame is due according
+/// to [`Frame::timestamp`]. Frames without a timestamp are passed on immediately, and a
+/// timestamp going backwards restarts the clock.
+pub struct PacedStream<I> {
+    frames: I,
+    speed: f64,
+    // When the first paced frame was yielded, and its timestamp.
+    anchor: Option<(Instant, Duration)>,
+}
+
+impl<I: Iterator<Item = Result<Frame, FlirError>>> PacedStream<I> {
+    pub fn new(frames: I) -> Self {
+        PacedStream {
+            frames,
+            speed: 1.0,
+            anchor: None,
+        }
+    }
+
+    /// Plays back `speed` times faster than real time, e.g. `0.5` or `2.0`. Values that
+    /// are not positive disable pacing.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    fn wait_for(&mut self, timestamp: Duration) {
+        if self.speed <= 0.0 {
+            return;
+        }
+        let (start, first) = match self.anchor {
+            Some((start, first)) if timestamp >= first => (start, first),
+            _ => {
+                self.anchor = Some((Instant::now(), timestamp));
+                return;
+            }
+        };
+        let due = start + (timestamp - first).div_f64(self.speed);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<Frame, FlirError>>> Iterator for PacedStream<I> {
+    type Item = Result<Frame, FlirError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        if let Ok(frame) = &frame {
+            if let Some(timestamp) = frame.timestamp() {
+                self.wait_for(timestamp);
+            }
+        }
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::frames_missed;
+
+    #[test]
+    fn frames_missed_survives_the_wrap() {
+        assert_eq!(frames_missed(41, 42), 0);
+        assert_eq!(frames_missed(41, 44), 2);
+        assert_eq!(frames_missed(u32::MAX, 0), 0);
+        assert_eq!(frames_missed(u32::MAX - 1, 1), 2);
+        assert_eq!(frames_missed(7, 7), 0);
+        assert_eq!(frames_missed(1000, 3), 0);
+        assert_eq!(frames_missed(3, 1000), 996);
+    }
+}
@@ -0,0 +1,512 @@
+//! Helpers for the crate's tests and for downstream tests that want frames in the FRAME
+//! endpoint's layout, or a camera to talk to without hardware. Only built with the
+//! `test-support` feature, which the crate's own tests and benches enable.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    descriptor::StringDescriptors,
+    endpoints,
+    frame::{FRAME_MAGIC, HEADER_LEN, THERMAL_HEIGHT, THERMAL_WIDTH},
+    message,
+    transport::{ControlTransfer, EndpointInfo, Transport},
+    FlirError, FlirOne, FlirOneBuilder, Frame, FLIR_ONE_PRODUCT_IDS, FLIR_VENDOR_ID,
+};
+
+/// Directory holding the recorded frames, `tests/fixtures` in this crate.
+pub fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Reads and parses `tests/fixtures/<name>.raw`, a frame as the FRAME endpoint delivers it;
+/// see `tests/fixtures/README.md` for where each one came from. Panics if the fixture is
+/// missing or does not parse.
+pub fn load_fixture(name: &str) -> Frame {
+    let path = fixtures_dir().join(name).with_extension("raw");
+    let bytes = fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {e}", path.display()));
+    Frame::parse(&bytes).unwrap_or_else(|e| panic!("parsing {}: {e}", path.display()))
+}
+
+/// Raw count of the background in [`FakeCamera`] frames.
+pub const BACKGROUND_RAW: u16 = 7000;
+/// Raw count of the hot spot in [`FakeCamera`] frames.
+pub const HOTSPOT_RAW: u16 = 9000;
+/// Battery charge a [`FakeCamera`] reports. It is never charging.
+pub const FAKE_BATTERY_PERCENTAGE: u8 = 80;
+
+const HOTSPOT_RADIUS: i64 = 3;
+const CONTROL_REQUEST_TYPE: u8 = 0x01;
+const CONTROL_REQUEST: u8 = 11;
+// Control transfer index of each protocol.
+const CONFIG_INDEX: u16 = 0;
+const FILEIO_INDEX: u16 = 1;
+const FRAME_INDEX: u16 = 2;
+const FAKE_INTERFACES: [u8; 3] = [0, 1, 2];
+/// Most file bytes a [`FakeCamera`] sends per `readFile` reply.
+const FAKE_FILE_CHUNK: usize = 4096;
+
+/// One read request on the FRAME endpoint of a [`FakeCamera`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRead {
+    /// Size of the read buffer.
+    pub requested: usize,
+    /// Bytes of the frame being sent that were still to come.
+    pub remaining: usize,
+}
+
+/// A scripted FLIR One for end-to-end tests. It answers CONFIG and FILEIO requests,
+/// reports ready once a protocol is started, and while the FRAME protocol runs sends one
+/// synthetic frame per read request, with a hot spot that moves along the middle row.
+#[derive(Debug, Clone)]
+pub struct FakeCamera {
+    streaming: [bool; 3],
+    frames_sent: usize,
+    pending_frame: Vec<u8>,
+    frame_reads: Vec<FrameRead>,
+    replies: HashMap<u8, VecDeque<u8>>,
+    options: BTreeMap<String, Value>,
+    files: BTreeMap<String, Vec<u8>>,
+    /// Open FILEIO streams: the file and, for reads, how far it has been sent.
+    streams: HashMap<u64, (String, usize)>,
+    next_stream: u64,
+    log: CameraLog,
+}
+
+/// The control transfers and requests a [`FakeCamera`] received and the interfaces
+/// claimed on it.
+/// Clones share the same record, so it can still be inspected after the camera has been
+/// dropped along with its [`FlirOne`].
+#[derive(Debug, Clone, Default)]
+pub struct CameraLog(Arc<Mutex<CameraLogState>>);
+
+#[derive(Debug, Default)]
+struct CameraLogState {
+    controls: Vec<ControlTransfer>,
+    claimed: Vec<u8>,
+    released: Vec<u8>,
+    reattached: Vec<u8>,
+    requests: Vec<String>,
+}
+
+impl CameraLog {
+    pub fn controls(&self) -> Vec<ControlTransfer> {
+        self.state().controls.clone()
+    }
+
+    /// Interfaces claimed and not released since.
+    pub fn claimed(&self) -> Vec<u8> {
+        self.state().claimed.clone()
+    }
+
+    /// Interfaces released, in order.
+    pub fn released(&self) -> Vec<u8> {
+        self.state().released.clone()
+    }
+
+    /// Types of the CONFIG and FILEIO requests received, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.state().requests.clone()
+    }
+
+    /// Interfaces handed back to their kernel driver, in order.
+    pub fn reattached(&self) -> Vec<u8> {
+        self.state().reattached.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, CameraLogState> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for FakeCamera {
+    fn default() -> Self {
+        FakeCamera::new()
+    }
+}
+
+impl FakeCamera {
+    pub fn new() -> Self {
+        FakeCamera {
+            streaming: [false; 3],
+            frames_sent: 0,
+            pending_frame: Vec::new(),
+            frame_reads: Vec::new(),
+            replies: HashMap::new(),
+            options: BTreeMap::from([
+                ("gainMode".to_owned(), json!("high")),
+                ("firmwareVersion".to_owned(), json!("3.0.1")),
+                ("hardwareRevision".to_owned(), json!("B")),
+            ]),
+            files: BTreeMap::new(),
+            streams: HashMap::new(),
+            next_stream: 1,
+            log: CameraLog::default(),
+        }
+    }
+
+    /// A [`FlirOne`] on this camera, with the usual endpoint addresses and its interfaces
+    /// claimed.
+    pub fn into_device(self) -> FlirOne<FakeCamera> {
+        let mut builder = FlirOneBuilder::new(self);
+        for interface in FAKE_INTERFACES {
+            builder
+                .claim(interface)
+                .expect("fake camera interfaces can be claimed");
+        }
+        builder
+            .config_read(EndpointInfo::new(endpoints::CONFIG_READ, 512))
+            .config_write(EndpointInfo::new(endpoints::CONFIG_WRITE, 512))
+            .fileio_read(EndpointInfo::new(endpoints::FILEIO_READ, 512))
+            .fileio_write(EndpointInfo::new(endpoints::FILEIO_WRITE, 512))
+            .frame_read(EndpointInfo::new(endpoints::FRAME_READ, 512))
+            .frame_write(EndpointInfo::new(endpoints::FRAME_WRITE, 512))
+            .build()
+            .expect("fake camera has every endpoint")
+    }
+
+    /// Where the hot spot is in the `index`th frame sent.
+    pub fn hotspot(index: usize) -> (u32, u32) {
+        let span = THERMAL_WIDTH as usize - 2 * HOTSPOT_RADIUS as usize;
+        (
+            (HOTSPOT_RADIUS as usize + index * 5 % span) as u32,
+            THERMAL_HEIGHT / 2,
+        )
+    }
+
+    /// A complete frame with the hot spot at `(x, y)`.
+    pub fn frame_bytes(hotspot: (u32, u32)) -> Vec<u8> {
+        let (hx, hy) = (hotspot.0 as i64, hotspot.1 as i64);
+        let mut thermal = Vec::new();
+        for y in 0..THERMAL_HEIGHT as i64 {
+            thermal.extend_from_slice(&[0; 4]);
+            for x in 0..THERMAL_WIDTH as i64 {
+                if x == THERMAL_WIDTH as i64 / 2 {
+                    thermal.extend_from_slice(&[0; 4]);
+                }
+                let near = (x - hx).abs() <= HOTSPOT_RADIUS && (y - hy).abs() <= HOTSPOT_RADIUS;
+                let noise = ((x * 7 + y * 13) % 16) as u16;
+                let raw = if near {
+                    HOTSPOT_RAW
+                } else {
+                    BACKGROUND_RAW + noise
+                };
+                thermal.extend_from_slice(&raw.to_le_bytes());
+            }
+        }
+        let jpeg = [0xff, 0xd8, 0xff, 0xd9];
+        let status = br#"{"shutterState":"ON","ffcState":"FFC_VALID_RAD"}"#;
+
+        let mut frame = FRAME_MAGIC.to_vec();
+        frame.extend_from_slice(&[0; 4]);
+        let size = thermal.len() + jpeg.len() + status.len();
+        for len in [size, thermal.len(), jpeg.len(), status.len()] {
+            frame.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+        frame.resize(HEADER_LEN, 0);
+        frame.extend(thermal);
+        frame.extend(jpeg);
+        frame.extend(status);
+        frame
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.streaming[FRAME_INDEX as usize]
+    }
+
+    pub fn frames_sent(&self) -> usize {
+        self.frames_sent
+    }
+
+    /// Every read request on the FRAME endpoint while streaming, oldest first.
+    pub fn frame_reads(&self) -> &[FrameRead] {
+        &self.frame_reads
+    }
+
+    pub fn controls(&self) -> Vec<ControlTransfer> {
+        self.log.controls()
+    }
+
+    pub fn claimed(&self) -> Vec<u8> {
+        self.log.claimed()
+    }
+
+    /// A handle on what the camera did that outlives it.
+    pub fn log(&self) -> CameraLog {
+        self.log.clone()
+    }
+
+    pub fn option(&self, key: &str) -> Option<&Value> {
+        self.options.get(key)
+    }
+
+    /// Stores a file for FILEIO to serve, e.g. `"Images/FLIR0001.jpg"`.
+    pub fn with_file(mut self, path: &str, contents: Vec<u8>) -> Self {
+        self.files.insert(path.to_owned(), contents);
+        self
+    }
+
+    /// A file as stored or as last written over FILEIO.
+    pub fn file(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(Vec::as_slice)
+    }
+
+    fn reply(&mut self, endpoint: u8, kind: &str, data: Value) {
+        self.reply_json(endpoint, &json!({ "type": kind, "data": data }), &[]);
+    }
+
+    fn reply_json(&mut self, endpoint: u8, json: &Value, payload: &[u8]) {
+        self.replies
+            .entry(endpoint)
+            .or_default()
+            .extend(message::encode(&json.to_string(), payload));
+    }
+
+    /// Answers the FILEIO requests, or returns `None` for anything else.
+    fn handle_file_request(
+        &mut self,
+        reply_to: u8,
+        kind: &str,
+        data: &Value,
+        payload: &[u8],
+    ) -> Option<()> {
+        let stream = data.get("streamIdentifier").and_then(Value::as_u64);
+        let reply = match kind {
+            "listDirectory" => {
+                let dir = format!("{}/", data.get("path")?.as_str()?);
+                let files: Vec<_> = self
+                    .files
+                    .keys()
+                    .filter_map(|path| path.strip_prefix(&dir))
+                    .filter(|name| !name.contains('/'))
+                    .collect();
+                json!({ "files": files })
+            }
+            "openFile" => {
+                let path = data.get("path")?.as_str()?.to_owned();
+                if data.get("mode")?.as_str()? == "w" {
+                    self.files.insert(path.clone(), Vec::new());
+                }
+                let Some(size) = self.files.get(&path).map(Vec::len) else {
+                    let error = json!({ "type": kind, "error": "no such file" });
+                    self.reply_json(reply_to, &error, &[]);
+                    return Some(());
+                };
+                let id = self.next_stream;
+                self.next_stream += 1;
+                self.streams.insert(id, (path, 0));
+                json!({ "streamIdentifier": id, "size": size })
+            }
+            "readFile" => {
+                let (path, sent) = self.streams.get_mut(&stream?)?;
+                let contents = &self.files[path.as_str()];
+                let chunk = contents[*sent..].len().min(FAKE_FILE_CHUNK);
+                let range = *sent..*sent + chunk;
+                *sent += chunk;
+                let contents = contents[range].to_vec();
+                let json = json!({ "type": kind, "data": { "size": chunk } });
+                self.reply_json(reply_to, &json, &contents);
+                return Some(());
+            }
+            "writeFile" => {
+                let (path, _) = self.streams.get(&stream?)?;
+                let size = data.get("size")?.as_u64()? as usize;
+                self.files
+                    .get_mut(path)?
+                    .extend_from_slice(&payload[..size.min(payload.len())]);
+                json!({})
+            }
+            "closeFile" => {
+                self.streams.remove(&stream?);
+                json!({})
+            }
+            _ => return None,
+        };
+        self.reply(reply_to, kind, reply);
+        Some(())
+    }
+
+    fn handle_request(&mut self, reply_to: u8, request: &[u8]) -> Result<(), FlirError> {
+        let Some(len) = message::payload_len(request)? else {
+            return Err(FlirError::Protocol("short request".to_owned()));
+        };
+        let payload = &request[message::HEADER_LEN..(message::HEADER_LEN + len).min(request.len())];
+        let (json, data_payload) = message::split_payload(payload)?;
+        let kind = json
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned();
+        self.log.state().requests.push(kind.clone());
+        let data = json.get("data").cloned().unwrap_or(Value::Null);
+        if self
+            .handle_file_request(reply_to, &kind, &data, data_payload)
+            .is_some()
+        {
+            return Ok(());
+        }
+        let key = data
+            .get("option")
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+        let reply = match (kind.as_str(), key) {
+            ("setOption", Some(key)) => {
+                let value = data.get("value").cloned().unwrap_or(Value::Null);
+                self.options.insert(key, value);
+                json!({})
+            }
+            ("getOption", Some(key)) => {
+                json!({ "value": self.options.get(&key).cloned().unwrap_or(Value::Null) })
+            }
+            ("listOptions", _) => json!({
+                "options": [{ "key": "gainMode", "type": "enum", "values": ["high", "low", "auto"] }]
+            }),
+            ("getBattery", _) => {
+                json!({ "percentage": FAKE_BATTERY_PERCENTAGE, "charging": false })
+            }
+            ("setPalette", _) => {
+                let palette = data.get("palette").cloned().unwrap_or(Value::Null);
+                self.options.insert("palette".to_owned(), palette);
+                json!({})
+            }
+            _ => json!({}),
+        };
+        self.reply(reply_to, &kind, reply);
+        Ok(())
+    }
+}
+
+impl Transport for FakeCamera {
+    fn read_bulk(
+        &mut self,
+        endpoint: u8,
+        buf: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        if endpoint == endpoints::FRAME_READ {
+            if !self.is_streaming() {
+                return Err(FlirError::Usb(rusb::Error::Timeout));
+            }
+            if self.pending_frame.is_empty() {
+                self.pending_frame = FakeCamera::frame_bytes(FakeCamera::hotspot(self.frames_sent));
+                self.frames_sent += 1;
+            }
+            self.frame_reads.push(FrameRead {
+                requested: buf.len(),
+                remaining: self.pending_frame.len(),
+            });
+            let n = buf.len().min(self.pending_frame.len());
+            buf[..n].copy_from_slice(&self.pending_frame[..n]);
+            self.pending_frame.drain(..n);
+            return Ok(n);
+        }
+        let queue = self.replies.entry(endpoint).or_default();
+        if queue.is_empty() {
+            return Err(FlirError::Usb(rusb::Error::Timeout));
+        }
+        let n = buf.len().min(queue.len());
+        for (slot, byte) in buf.iter_mut().zip(queue.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
+    }
+
+    fn write_bulk(
+        &mut self,
+        endpoint: u8,
+        data: &[u8],
+        _timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        let reply_to = match endpoint {
+            endpoints::CONFIG_WRITE => endpoints::CONFIG_READ,
+            endpoints::FILEIO_WRITE => endpoints::FILEIO_READ,
+            _ => return Ok(data.len()),
+        };
+        self.handle_request(reply_to, data)?;
+        Ok(data.len())
+    }
+
+    fn write_control(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &[u8],
+        _timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        self.log.state().controls.push(ControlTransfer {
+            request_type,
+            request,
+            value,
+            index,
+            data: data.to_vec(),
+        });
+        if request_type != CONTROL_REQUEST_TYPE || request != CONTROL_REQUEST {
+            return Err(FlirError::Usb(rusb::Error::Pipe));
+        }
+        let protocol = match index {
+            CONFIG_INDEX | FILEIO_INDEX | FRAME_INDEX => index as usize,
+            _ => return Err(FlirError::Usb(rusb::Error::Pipe)),
+        };
+        let start = value == 1;
+        self.streaming[protocol] = start;
+        if start {
+            self.reply(
+                endpoints::CONFIG_READ,
+                "cameraStatus",
+                json!({ "ready": true }),
+            );
+            if index == CONFIG_INDEX {
+                self.reply(
+                    endpoints::CONFIG_READ,
+                    "batteryVoltageUpdate",
+                    json!({ "percentage": FAKE_BATTERY_PERCENTAGE, "chargingState": "noCharging" }),
+                );
+            }
+        } else if index == FRAME_INDEX {
+            self.pending_frame.clear();
+        }
+        Ok(data.len())
+    }
+
+    fn claim_interface(&mut self, interface: u8) -> Result<(), FlirError> {
+        let mut state = self.log.state();
+        if !state.claimed.contains(&interface) {
+            state.claimed.push(interface);
+        }
+        Ok(())
+    }
+
+    fn release_interface(&mut self, interface: u8) -> Result<(), FlirError> {
+        let mut state = self.log.state();
+        state.claimed.retain(|&claimed| claimed != interface);
+        state.released.push(interface);
+        Ok(())
+    }
+
+    fn attach_kernel_driver(&mut self, interface: u8) -> Result<(), FlirError> {
+        self.log.state().reattached.push(interface);
+        Ok(())
+    }
+
+    fn device_ids(&self) -> Result<(u16, u16), FlirError> {
+        Ok((FLIR_VENDOR_ID, FLIR_ONE_PRODUCT_IDS[0]))
+    }
+
+    fn string_descriptors(&self) -> Result<StringDescriptors, FlirError> {
+        Ok(StringDescriptors {
+            language: crate::descriptor::DEFAULT_LANGUAGE,
+            manufacturer: Some("FLIR Systems".to_owned()),
+            product: Some("FLIR One (fake)".to_owned()),
+            serial_number: Some("FAKE0001".to_owned()),
+        })
+    }
+}
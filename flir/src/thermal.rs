@@ -0,0 +1,680 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Cursor,
+};
+
+use image::{imageops, ImageFormat, Rgb, RgbImage};
+
+use crate::{
+    frame::{Frame, THERMAL_HEIGHT, THERMAL_WIDTH},
+    radiometry::{check_emissivity, TempUnit},
+    tiff, Calibration, ColorScale, FlirError, Palette, PaletteLut,
+};
+
+// Each sensor row is 164 little-endian words: two leading words, 80 pixels, two more
+// words, then the other 80 pixels.
+const THERMAL_ROW_WORDS: usize = 164;
+const THERMAL_ROW_LEAD: usize = 2;
+const THERMAL_ROW_GAP: usize = 2;
+/// Mean difference between horizontal neighbours, in raw counts, above which an image is
+/// taken to be noise. Real scenes change gradually; swapped bytes put the fast-changing
+/// low byte at the top and push this into the thousands.
+const MAX_MEAN_STEP: f64 = 256.0;
+
+/// Byte order of the words in a frame's thermal section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Endianness {
+    /// What the FLIR One firmware sends.
+    #[default]
+    Little,
+    Big,
+}
+
+/// Coordinates of defective sensor pixels.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BadPixelMap {
+    pixels: Vec<(u32, u32)>,
+}
+
+impl BadPixelMap {
+    pub fn new(pixels: Vec<(u32, u32)>) -> Self {
+        BadPixelMap { pixels }
+    }
+
+    pub fn pixels(&self) -> &[(u32, u32)] {
+        &self.pixels
+    }
+
+    /// Flags pixels that differ from the median of their neighbours by more than
+    /// `threshold` raw counts.
+    pub fn detect(image: &ThermalImage, threshold: u16) -> Self {
+        let mut pixels = Vec::new();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let mut around: Vec<u16> = image
+                    .neighbours(x, y)
+                    .map(|(nx, ny)| image.pixels[image.index(nx, ny)])
+                    .collect();
+                let Some(median) = median(&mut around) else {
+                    continue;
+                };
+                if image.pixels[image.index(x, y)].abs_diff(median) > threshold {
+                    pixels.push((x, y));
+                }
+            }
+        }
+        BadPixelMap { pixels }
+    }
+}
+
+fn median(values: &mut [u16]) -> Option<u16> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[values.len() / 2])
+}
+
+/// A region of an image, in pixels from the top left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// A clockwise rotation by a multiple of 90 degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    /// Rotates a visual image, e.g. from [`Frame::visual_image`], the same way
+    /// [`ThermalImage::rotate`] rotates the thermal one.
+    pub fn apply(self, image: &RgbImage) -> RgbImage {
+        match self {
+            Rotation::Deg90 => imageops::rotate90(image),
+            Rotation::Deg180 => imageops::rotate180(image),
+            Rotation::Deg270 => imageops::rotate270(image),
+        }
+    }
+}
+
+/// How far the camera is turned clockwise from upright, as its accelerometer reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Upright,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Orientation {
+    /// The nearest quarter turn to `degrees` clockwise.
+    pub fn from_degrees(degrees: f64) -> Option<Self> {
+        if !degrees.is_finite() {
+            return None;
+        }
+        Some(match ((degrees / 90.0).round() as i64).rem_euclid(4) {
+            0 => Orientation::Upright,
+            1 => Orientation::Deg90,
+            2 => Orientation::Deg180,
+            _ => Orientation::Deg270,
+        })
+    }
+
+    /// The [`Rotation`] that turns images taken in this orientation upright.
+    pub fn upright_rotation(self) -> Option<Rotation> {
+        match self {
+            Orientation::Upright => None,
+            Orientation::Deg90 => Some(Rotation::Deg270),
+            Orientation::Deg180 => Some(Rotation::Deg180),
+            Orientation::Deg270 => Some(Rotation::Deg90),
+        }
+    }
+}
+
+/// Raw 16-bit sensor counts in row-major order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThermalImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u16>,
+}
+
+impl ThermalImage {
+    /// Value a pixel of a [`ThermalImage::diff`] result takes when both inputs agree.
+    pub const DIFF_ZERO: u16 = 0x8000;
+
+    pub fn new(width: u32, height: u32, pixels: Vec<u16>) -> Result<Self, FlirError> {
+        let expected = width as usize * height as usize;
+        if pixels.len() != expected {
+            return Err(FlirError::BufferSize {
+                expected,
+                found: pixels.len(),
+            });
+        }
+        Ok(ThermalImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decodes the raw thermal section of a frame.
+    pub fn from_frame(frame: &Frame) -> Result<Self, FlirError> {
+        ThermalImage::from_frame_with(frame, Endianness::Little)
+    }
+
+    /// Decodes the thermal section, and if it does not
+    /// [look valid](ThermalImage::looks_valid) tries the opposite byte order, for firmware
+    /// variants that swap it. Returns the byte order used; little-endian when neither
+    /// looks valid.
+    pub fn from_frame_detect(frame: &Frame) -> Result<(Self, Endianness), FlirError> {
+        let little = ThermalImage::from_frame_with(frame, Endianness::Little)?;
+        if little.looks_valid() {
+            return Ok((little, Endianness::Little));
+        }
+        let big = ThermalImage::from_frame_with(frame, Endianness::Big)?;
+        if big.looks_valid() {
+            return Ok((big, Endianness::Big));
+        }
+        Ok((little, Endianness::Little))
+    }
+
+    pub fn from_frame_with(frame: &Frame, endianness: Endianness) -> Result<Self, FlirError> {
+        let raw = &frame.thermal_raw;
+        let needed = THERMAL_ROW_WORDS * THERMAL_HEIGHT as usize * 2;
+        if raw.len() < needed {
+            return Err(FlirError::BufferSize {
+                expected: needed,
+                found: raw.len(),
+            });
+        }
+        let word = match endianness {
+            Endianness::Little => u16::from_le_bytes,
+            Endianness::Big => u16::from_be_bytes,
+        };
+        let half = THERMAL_WIDTH as usize / 2;
+        let mut pixels = Vec::with_capacity((THERMAL_WIDTH * THERMAL_HEIGHT) as usize);
+        for row in raw
+            .chunks_exact(THERMAL_ROW_WORDS * 2)
+            .take(THERMAL_HEIGHT as usize)
+        {
+            let words = |from: usize| {
+                row[from * 2..(from + half) * 2]
+                    .chunks_exact(2)
+                    .map(|b| word([b[0], b[1]]))
+            };
+            pixels.extend(words(THERMAL_ROW_LEAD));
+            pixels.extend(words(THERMAL_ROW_LEAD + half + THERMAL_ROW_GAP));
+        }
+        ThermalImage::new(THERMAL_WIDTH, THERMAL_HEIGHT, pixels)
+    }
+
+    /// Whether the pixels resemble a thermal scene: not stuck at zero or full scale, and
+    /// changing gradually between neighbours rather than looking like noise.
+    pub fn looks_valid(&self) -> bool {
+        let (Some(&min), Some(&max)) = (self.pixels.iter().min(), self.pixels.iter().max()) else {
+            return false;
+        };
+        if min == max && (min == 0 || min == u16::MAX) {
+            return false;
+        }
+        let (mut steps, mut total) = (0u64, 0u64);
+        for row in self.pixels.chunks_exact(self.width.max(1) as usize) {
+            for pair in row.windows(2) {
+                total += pair[0].abs_diff(pair[1]) as u64;
+                steps += 1;
+            }
+        }
+        steps == 0 || (total as f64 / steps as f64) <= MAX_MEAN_STEP
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u16] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<u16> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.pixels[self.index(x, y)])
+    }
+
+    /// Standard deviation of the raw counts.
+    pub fn std_dev(&self) -> f64 {
+        if self.pixels.is_empty() {
+            return 0.0;
+        }
+        let n = self.pixels.len() as f64;
+        let mean = self.pixels.iter().map(|&p| p as f64).sum::<f64>() / n;
+        let variance = self
+            .pixels
+            .iter()
+            .map(|&p| (p as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        variance.sqrt()
+    }
+
+    /// Temperature of every pixel in degrees Celsius, in the same order as [`ThermalImage::pixels`].
+    pub fn to_celsius(&self, cal: &Calibration) -> Vec<f32> {
+        let conversion = cal.conversion();
+        self.pixels.iter().map(|&p| conversion.celsius(p)).collect()
+    }
+
+    /// [`ThermalImage::to_celsius`] with each pixel's emissivity taken from
+    /// `emissivity_map`, row-major like the pixels, instead of from `cal`. For scenes with
+    /// several materials.
+    pub fn to_celsius_with_map(
+        &self,
+        cal: &Calibration,
+        emissivity_map: &[f32],
+    ) -> Result<Vec<f32>, FlirError> {
+        if emissivity_map.len() != self.pixels.len() {
+            return Err(FlirError::BufferSize {
+                expected: self.pixels.len(),
+                found: emissivity_map.len(),
+            });
+        }
+        for &emissivity in emissivity_map {
+            check_emissivity(emissivity)?;
+        }
+        // Maps tend to hold a handful of materials, so convert once per emissivity.
+        let mut conversions = HashMap::new();
+        Ok(self
+            .pixels
+            .iter()
+            .zip(emissivity_map)
+            .map(|(&p, &emissivity)| {
+                conversions
+                    .entry(emissivity.to_bits())
+                    .or_insert_with(|| {
+                        let mut cal = *cal;
+                        cal.object.emissivity = emissivity;
+                        cal.conversion()
+                    })
+                    .celsius(p)
+            })
+            .collect())
+    }
+
+    /// Object radiance of every pixel, see [`Calibration::raw_to_radiance`] for its units.
+    pub fn to_radiance(&self, cal: &Calibration) -> Vec<f32> {
+        let conversion = cal.conversion();
+        self.pixels
+            .iter()
+            .map(|&p| conversion.radiance(p as f64) as f32)
+            .collect()
+    }
+
+    /// Temperature at fractional pixel coordinates, interpolating the raw counts of the
+    /// four nearest pixels. Coordinates outside the image are clamped to its edges; an
+    /// empty image gives NaN.
+    pub fn sample_celsius(&self, x: f32, y: f32, cal: &Calibration) -> f32 {
+        if self.pixels.is_empty() {
+            return f32::NAN;
+        }
+        let x = x.clamp(0.0, self.width.saturating_sub(1) as f32);
+        let y = y.clamp(0.0, self.height.saturating_sub(1) as f32);
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = ((x - x0 as f32) as f64, (y - y0 as f32) as f64);
+        let raw = |x, y| self.pixels[self.index(x, y)] as f64;
+        let top = raw(x0, y0) * (1.0 - tx) + raw(x1, y0) * tx;
+        let bottom = raw(x0, y1) * (1.0 - tx) + raw(x1, y1) * tx;
+        cal.conversion()
+            .celsius_interpolated(top * (1.0 - ty) + bottom * ty)
+    }
+
+    /// [`ThermalImage::to_celsius`] in `unit`.
+    pub fn to_temperatures(&self, cal: &Calibration, unit: TempUnit) -> Vec<f32> {
+        let conversion = cal.conversion();
+        self.pixels
+            .iter()
+            .map(|&p| unit.from_celsius(conversion.celsius(p)))
+            .collect()
+    }
+
+    /// Raw counts as a `(height, width)` array.
+    #[cfg(feature = "ndarray")]
+    pub fn to_array2(&self) -> ndarray::Array2<u16> {
+        ndarray::Array2::from_shape_vec(
+            (self.height as usize, self.width as usize),
+            self.pixels.clone(),
+        )
+        .expect("pixel count matches dimensions")
+    }
+
+    /// Temperatures in degrees Celsius as a `(height, width)` array.
+    #[cfg(feature = "ndarray")]
+    pub fn to_celsius_array2(&self, cal: &Calibration) -> ndarray::Array2<f32> {
+        ndarray::Array2::from_shape_vec(
+            (self.height as usize, self.width as usize),
+            self.to_celsius(cal),
+        )
+        .expect("pixel count matches dimensions")
+    }
+
+    /// Replaces each pixel in `map` with the median of its neighbours that are not
+    /// themselves listed. Coordinates outside the image are ignored.
+    pub fn correct_bad_pixels(&mut self, map: &BadPixelMap) {
+        let bad: HashSet<(u32, u32)> = map.pixels.iter().copied().collect();
+        let original = self.pixels.clone();
+        for &(x, y) in &map.pixels {
+            if x >= self.width || y >= self.height {
+                continue;
+            }
+            let mut around: Vec<u16> = self
+                .neighbours(x, y)
+                .filter(|p| !bad.contains(p))
+                .map(|(nx, ny)| original[self.index(nx, ny)])
+                .collect();
+            if let Some(median) = median(&mut around) {
+                let i = self.index(x, y);
+                self.pixels[i] = median;
+            }
+        }
+    }
+
+    pub fn rotate(&self, rotation: Rotation) -> ThermalImage {
+        let (w, h) = (self.width, self.height);
+        match rotation {
+            Rotation::Deg90 => self.remap(h, w, |x, y| (y, h - 1 - x)),
+            Rotation::Deg180 => self.remap(w, h, |x, y| (w - 1 - x, h - 1 - y)),
+            Rotation::Deg270 => self.remap(h, w, |x, y| (w - 1 - y, x)),
+        }
+    }
+
+    /// The pixels inside `rect`, which must be non-empty and lie within the image.
+    pub fn crop(&self, rect: Rect) -> Result<ThermalImage, FlirError> {
+        let fits = rect.width > 0
+            && rect.height > 0
+            && rect
+                .x
+                .checked_add(rect.width)
+                .is_some_and(|right| right <= self.width)
+            && rect
+                .y
+                .checked_add(rect.height)
+                .is_some_and(|bottom| bottom <= self.height);
+        if !fits {
+            return Err(FlirError::OutOfBounds {
+                rect,
+                bounds: (self.width, self.height),
+            });
+        }
+        Ok(self.remap(rect.width, rect.height, |x, y| (rect.x + x, rect.y + y)))
+    }
+
+    /// Mirrors the image left to right. The visual image matches after
+    /// [`image::imageops::flip_horizontal`].
+    pub fn flip_horizontal(&self) -> ThermalImage {
+        self.remap(self.width, self.height, |x, y| (self.width - 1 - x, y))
+    }
+
+    /// Mirrors the image top to bottom. The visual image matches after
+    /// [`image::imageops::flip_vertical`].
+    pub fn flip_vertical(&self) -> ThermalImage {
+        self.remap(self.width, self.height, |x, y| (x, self.height - 1 - y))
+    }
+
+    /// Shrinks the image to fit within `max_dim` pixels on both sides, keeping its aspect
+    /// ratio. Each pixel is the mean raw count of the area it covers, so it still converts
+    /// to a meaningful temperature. Images that already fit are returned unchanged.
+    pub fn thumbnail(&self, max_dim: u32) -> ThermalImage {
+        let max_dim = max_dim.max(1);
+        let longest = self.width.max(self.height);
+        if longest <= max_dim {
+            return self.clone();
+        }
+        let fit = |side: u32| ((side as u64 * max_dim as u64 / longest as u64) as u32).max(1);
+        let (width, height) = (fit(self.width), fit(self.height));
+        // Source span [start, end) covered by target pixel `i` of `target` along `side`.
+        let span = |i: u32, target: u32, side: u32| {
+            let start = i as u64 * side as u64 / target as u64;
+            let end = ((i as u64 + 1) * side as u64 / target as u64).max(start + 1);
+            start as u32..end as u32
+        };
+
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            let rows = span(y, height, self.height);
+            for x in 0..width {
+                let columns = span(x, width, self.width);
+                let (mut sum, mut count) = (0u64, 0u64);
+                for sy in rows.clone() {
+                    for sx in columns.clone() {
+                        sum += self.pixels[self.index(sx, sy)] as u64;
+                        count += 1;
+                    }
+                }
+                pixels.push((sum / count) as u16);
+            }
+        }
+        ThermalImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Removes fixed-pattern noise using a frame of a uniform target, such as the closed
+    /// shutter: each pixel is shifted by how far the reference deviates from its mean.
+    pub fn subtract_flatfield(&mut self, reference: &ThermalImage) -> Result<(), FlirError> {
+        self.check_dimensions(reference)?;
+        if reference.pixels.is_empty() {
+            return Ok(());
+        }
+        let mean =
+            reference.pixels.iter().map(|&p| p as f64).sum::<f64>() / reference.pixels.len() as f64;
+        for (p, &r) in self.pixels.iter_mut().zip(&reference.pixels) {
+            let corrected = *p as f64 - (r as f64 - mean);
+            *p = corrected.round().clamp(0.0, u16::MAX as f64) as u16;
+        }
+        Ok(())
+    }
+
+    /// Per-pixel `self - other` in raw counts, offset by [`ThermalImage::DIFF_ZERO`] so the
+    /// signed result fits the unsigned buffer. Render it with [`Palette::Diverging`]. Counts
+    /// are not linear in temperature; [`ThermalImage::diff_celsius`] gives degrees.
+    pub fn diff(&self, other: &ThermalImage) -> Result<ThermalImage, FlirError> {
+        self.check_dimensions(other)?;
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(&other.pixels)
+            .map(|(&a, &b)| {
+                let delta = a as i32 - b as i32 + Self::DIFF_ZERO as i32;
+                delta.clamp(0, u16::MAX as i32) as u16
+            })
+            .collect();
+        Ok(ThermalImage {
+            width: self.width,
+            height: self.height,
+            pixels,
+        })
+    }
+
+    /// Per-pixel `self - other` in degrees Celsius, both converted with `cal`. Unlike
+    /// [`ThermalImage::diff`], the result does not depend on where the counts sit on the
+    /// Planck curve.
+    pub fn diff_celsius(
+        &self,
+        other: &ThermalImage,
+        cal: &Calibration,
+    ) -> Result<Vec<f32>, FlirError> {
+        self.check_dimensions(other)?;
+        let conversion = cal.conversion();
+        Ok(self
+            .pixels
+            .iter()
+            .zip(&other.pixels)
+            .map(|(&a, &b)| conversion.celsius(a) - conversion.celsius(b))
+            .collect())
+    }
+
+    /// Stretches the image over the palette. Diverging palettes are centered on
+    /// [`ThermalImage::DIFF_ZERO`] so unchanged pixels stay neutral.
+    pub fn colorize(&self, palette: Palette) -> RgbImage {
+        let (min, max) = self.raw_range();
+        self.colorize_range(palette, min, max)
+    }
+
+    /// [`ThermalImage::colorize`] over the range `scale` picks. A [`ColorScale::Agc`]
+    /// state is updated with this image, so pass the same one for every frame.
+    pub fn colorize_scaled(&self, palette: Palette, scale: &mut ColorScale) -> RgbImage {
+        let (min, max) = match scale {
+            ColorScale::Auto => self.raw_range(),
+            ColorScale::Fixed { min, max } => (*min as f32, *max as f32),
+            ColorScale::Agc(agc) => {
+                let (min, max) = self.raw_range();
+                agc.update(min, max)
+            }
+        };
+        self.colorize_range(palette, min, max)
+    }
+
+    fn raw_range(&self) -> (f32, f32) {
+        let min = self.pixels.iter().copied().min().unwrap_or(0) as f32;
+        let max = self.pixels.iter().copied().max().unwrap_or(0) as f32;
+        (min, max)
+    }
+
+    /// The image as tightly packed RGBA8, ready to upload as a texture: rows top to
+    /// bottom, pixels left to right, 4 bytes each (alpha always 255), so the row stride is
+    /// `4 * width` bytes. A [`ColorScale::Agc`] state is used as it stands; update it
+    /// elsewhere, e.g. through [`ThermalImage::colorize_scaled`].
+    pub fn to_rgba(&self, palette: Palette, scale: &ColorScale) -> Vec<u8> {
+        let (min, max) = match scale {
+            ColorScale::Auto => self.raw_range(),
+            ColorScale::Fixed { min, max } => (*min as f32, *max as f32),
+            ColorScale::Agc(agc) => agc.range().unwrap_or_else(|| self.raw_range()),
+        };
+        let (low, span) = Self::palette_span(palette, min, max);
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for &v in &self.pixels {
+            rgba.extend_from_slice(&palette.color((v as f32 - low) / span));
+            rgba.push(u8::MAX);
+        }
+        rgba
+    }
+
+    fn colorize_range(&self, palette: Palette, min: f32, max: f32) -> RgbImage {
+        let (low, span) = Self::palette_span(palette, min, max);
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let v = self.pixels[self.index(x, y)] as f32;
+            Rgb(palette.color((v - low) / span))
+        })
+    }
+
+    /// Raw count at the bottom of the palette and the span it covers, for images between
+    /// `min` and `max`.
+    fn palette_span(palette: Palette, min: f32, max: f32) -> (f32, f32) {
+        let zero = Self::DIFF_ZERO as f32;
+        if palette.is_diverging() {
+            let half = (max - zero).abs().max((zero - min).abs()).max(1.0);
+            (zero - half, 2.0 * half)
+        } else {
+            (min, (max - min).max(1.0))
+        }
+    }
+
+    /// [`ThermalImage::colorize`] with a color table instead of a built-in palette.
+    pub fn colorize_lut(&self, lut: &PaletteLut) -> RgbImage {
+        let (min, max) = self.raw_range();
+        let span = (max - min).max(1.0);
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let v = self.pixels[self.index(x, y)] as f32;
+            Rgb(lut.color((v - min) / span))
+        })
+    }
+
+    /// [`ThermalImage::colorize`] encoded as PNG.
+    pub fn to_png(&self, palette: Palette) -> Result<Vec<u8>, FlirError> {
+        let mut png = Cursor::new(Vec::new());
+        self.colorize(palette)
+            .write_to(&mut png, ImageFormat::Png)?;
+        Ok(png.into_inner())
+    }
+
+    /// The raw counts as an uncompressed 16-bit grayscale TIFF, for analysis in tools
+    /// that read radiometric data.
+    pub fn to_tiff16(&self) -> Vec<u8> {
+        tiff::encode_gray16(self.width, self.height, &self.pixels)
+    }
+
+    fn check_dimensions(&self, other: &ThermalImage) -> Result<(), FlirError> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(FlirError::DimensionMismatch {
+                expected: (self.width, self.height),
+                found: (other.width, other.height),
+            });
+        }
+        Ok(())
+    }
+
+    /// Builds a `width` x `height` image whose pixel at `(x, y)` is this image's pixel at
+    /// `source(x, y)`.
+    fn remap(
+        &self,
+        width: u32,
+        height: u32,
+        source: impl Fn(u32, u32) -> (u32, u32),
+    ) -> ThermalImage {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = source(x, y);
+                pixels.push(self.pixels[self.index(sx, sy)]);
+            }
+        }
+        ThermalImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    fn neighbours(&self, x: u32, y: u32) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let (x, y) = (x as i64, y as i64);
+        (-1..=1)
+            .flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            .filter(move |&(nx, ny)| {
+                (nx, ny) != (x, y)
+                    && nx >= 0
+                    && ny >= 0
+                    && nx < self.width as i64
+                    && ny < self.height as i64
+            })
+            .map(|(nx, ny)| (nx as u32, ny as u32))
+    }
+}
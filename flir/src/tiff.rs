@@ -0,0 +1,61 @@
+//! A minimal baseline TIFF encoder for 16-bit grayscale images: little-endian, a single
+//! uncompressed strip. It is all raw radiometric exports need, without pulling in a full
+//! TIFF library.
+
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_RATIONAL: u16 = 5;
+
+const HEADER_LEN: u32 = 8;
+const ENTRIES: u16 = 12;
+
+/// Encodes `pixels`, row-major, as a `width` x `height` 16-bit grayscale TIFF.
+pub(crate) fn encode_gray16(width: u32, height: u32, pixels: &[u16]) -> Vec<u8> {
+    let ifd_len = 2 + 12 * ENTRIES as u32 + 4;
+    let resolution_at = HEADER_LEN + ifd_len;
+    let data_at = resolution_at + 16;
+    let data_len = pixels.len() as u32 * 2;
+
+    let mut tiff = Vec::with_capacity((data_at + data_len) as usize);
+    tiff.extend_from_slice(b"II");
+    tiff.extend_from_slice(&42u16.to_le_bytes());
+    tiff.extend_from_slice(&HEADER_LEN.to_le_bytes());
+
+    tiff.extend_from_slice(&ENTRIES.to_le_bytes());
+    let mut entry = |tag: u16, kind: u16, value: u32| {
+        tiff.extend_from_slice(&tag.to_le_bytes());
+        tiff.extend_from_slice(&kind.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        // A SHORT sits in the first two bytes of the value field.
+        match kind {
+            TYPE_SHORT => {
+                tiff.extend_from_slice(&(value as u16).to_le_bytes());
+                tiff.extend_from_slice(&[0; 2]);
+            }
+            _ => tiff.extend_from_slice(&value.to_le_bytes()),
+        }
+    };
+    // Tags must be in ascending order.
+    entry(256, TYPE_LONG, width); // ImageWidth
+    entry(257, TYPE_LONG, height); // ImageLength
+    entry(258, TYPE_SHORT, 16); // BitsPerSample
+    entry(259, TYPE_SHORT, 1); // Compression: none
+    entry(262, TYPE_SHORT, 1); // PhotometricInterpretation: black is zero
+    entry(273, TYPE_LONG, data_at); // StripOffsets
+    entry(277, TYPE_SHORT, 1); // SamplesPerPixel
+    entry(278, TYPE_LONG, height); // RowsPerStrip
+    entry(279, TYPE_LONG, data_len); // StripByteCounts
+    entry(282, TYPE_RATIONAL, resolution_at); // XResolution
+    entry(283, TYPE_RATIONAL, resolution_at + 8); // YResolution
+    entry(296, TYPE_SHORT, 1); // ResolutionUnit: none
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+
+    for _ in 0..2 {
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+    }
+    for pixel in pixels {
+        tiff.extend_from_slice(&pixel.to_le_bytes());
+    }
+    tiff
+}
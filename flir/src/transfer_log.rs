@@ -0,0 +1,69 @@
+//! Recorder for raw USB transfers, for comparing firmware behaviour without usbmon.
+//!
+//! The file starts with [`MAGIC`], followed by one record per transfer:
+//!
+//! | bytes | field                                                     |
+//! |-------|-----------------------------------------------------------|
+//! | 8     | microseconds since the Unix epoch, little-endian          |
+//! | 1     | direction: 0 = host to device, 1 = device to host         |
+//! | 1     | kind: 0 = control, 1 = bulk                               |
+//! | 1     | endpoint address (0 for control transfers)                |
+//! | 4     | length of the data that follows, little-endian            |
+//! | n     | data; control transfers start with their 8-byte setup     |
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const MAGIC: &[u8; 8] = b"FLIRLOG\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferKind {
+    Control,
+    Bulk,
+}
+
+#[derive(Debug)]
+pub struct TransferLog {
+    out: BufWriter<File>,
+}
+
+impl TransferLog {
+    /// Appends to `path`, writing the magic first if the file is new or empty.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut out = BufWriter::new(file);
+        if empty {
+            out.write_all(MAGIC)?;
+        }
+        Ok(TransferLog { out })
+    }
+
+    pub fn record(
+        &mut self,
+        kind: TransferKind,
+        endpoint: u8,
+        inbound: bool,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_micros() as u64);
+        self.out.write_all(&micros.to_le_bytes())?;
+        self.out.write_all(&[
+            inbound as u8,
+            match kind {
+                TransferKind::Control => 0,
+                TransferKind::Bulk => 1,
+            },
+            endpoint,
+        ])?;
+        self.out.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.out.write_all(data)?;
+        self.out.flush()
+    }
+}
@@ -0,0 +1,262 @@
+//! The USB operations [`FlirOne`](crate::FlirOne) needs, so it can run against something
+//! other than a real device.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use rusb::{DeviceHandle, EndpointDescriptor, LogLevel, UsbContext};
+
+use crate::{descriptor::StringDescriptors, FlirError, FLIR_ONE_PRODUCT_IDS, FLIR_VENDOR_ID};
+
+pub trait Transport {
+    fn read_bulk(
+        &mut self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError>;
+
+    fn write_bulk(
+        &mut self,
+        endpoint: u8,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError>;
+
+    fn write_control(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError>;
+
+    fn claim_interface(&mut self, interface: u8) -> Result<(), FlirError>;
+
+    fn release_interface(&mut self, interface: u8) -> Result<(), FlirError>;
+
+    /// Hands `interface` back to the kernel driver that was detached to claim it.
+    fn attach_kernel_driver(&mut self, interface: u8) -> Result<(), FlirError>;
+
+    /// Vendor and product ID of the device.
+    fn device_ids(&self) -> Result<(u16, u16), FlirError>;
+
+    fn string_descriptors(&self) -> Result<StringDescriptors, FlirError>;
+
+    fn set_log_level(&mut self, _level: LogLevel) {}
+}
+
+impl<C: UsbContext> Transport for DeviceHandle<C> {
+    fn read_bulk(
+        &mut self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        Ok(DeviceHandle::read_bulk(self, endpoint, buf, timeout)?)
+    }
+
+    fn write_bulk(
+        &mut self,
+        endpoint: u8,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        Ok(DeviceHandle::write_bulk(self, endpoint, data, timeout)?)
+    }
+
+    fn write_control(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        Ok(DeviceHandle::write_control(
+            self,
+            request_type,
+            request,
+            value,
+            index,
+            data,
+            timeout,
+        )?)
+    }
+
+    fn claim_interface(&mut self, interface: u8) -> Result<(), FlirError> {
+        Ok(DeviceHandle::claim_interface(self, interface)?)
+    }
+
+    fn release_interface(&mut self, interface: u8) -> Result<(), FlirError> {
+        Ok(DeviceHandle::release_interface(self, interface)?)
+    }
+
+    fn attach_kernel_driver(&mut self, interface: u8) -> Result<(), FlirError> {
+        Ok(DeviceHandle::attach_kernel_driver(self, interface)?)
+    }
+
+    fn device_ids(&self) -> Result<(u16, u16), FlirError> {
+        let descriptor = self.device().device_descriptor()?;
+        Ok((descriptor.vendor_id(), descriptor.product_id()))
+    }
+
+    fn string_descriptors(&self) -> Result<StringDescriptors, FlirError> {
+        StringDescriptors::read(self)
+    }
+
+    fn set_log_level(&mut self, level: LogLevel) {
+        self.context().clone().set_log_level(level);
+    }
+}
+
+/// Owned copy of the parts of an endpoint descriptor the driver uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointInfo {
+    pub address: u8,
+    pub max_packet_size: u16,
+}
+
+impl EndpointInfo {
+    pub fn new(address: u8, max_packet_size: u16) -> Self {
+        EndpointInfo {
+            address,
+            max_packet_size,
+        }
+    }
+}
+
+impl From<EndpointDescriptor<'_>> for EndpointInfo {
+    fn from(endpoint: EndpointDescriptor<'_>) -> Self {
+        EndpointInfo::new(endpoint.address(), endpoint.max_packet_size())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlTransfer {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub data: Vec<u8>,
+}
+
+/// A [`Transport`] that replays queued IN data and records everything written to it.
+/// Reads on an endpoint with nothing queued time out.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    reads: HashMap<u8, VecDeque<Vec<u8>>>,
+    writes: Vec<(u8, Vec<u8>)>,
+    controls: Vec<ControlTransfer>,
+    claimed: Vec<u8>,
+    strings: StringDescriptors,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        MockTransport::default()
+    }
+
+    /// Queues `data` to be returned by a read on `endpoint`. A read with a smaller buffer
+    /// leaves the rest for the next read.
+    pub fn push_read(&mut self, endpoint: u8, data: impl Into<Vec<u8>>) {
+        self.reads
+            .entry(endpoint)
+            .or_default()
+            .push_back(data.into());
+    }
+
+    pub fn set_string_descriptors(&mut self, strings: StringDescriptors) {
+        self.strings = strings;
+    }
+
+    pub fn writes(&self) -> &[(u8, Vec<u8>)] {
+        &self.writes
+    }
+
+    pub fn controls(&self) -> &[ControlTransfer] {
+        &self.controls
+    }
+
+    pub fn claimed(&self) -> &[u8] {
+        &self.claimed
+    }
+}
+
+impl Transport for MockTransport {
+    fn read_bulk(
+        &mut self,
+        endpoint: u8,
+        buf: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        let queue = self.reads.entry(endpoint).or_default();
+        let Some(mut data) = queue.pop_front() else {
+            return Err(FlirError::Usb(rusb::Error::Timeout));
+        };
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        if n < data.len() {
+            queue.push_front(data.split_off(n));
+        }
+        Ok(n)
+    }
+
+    fn write_bulk(
+        &mut self,
+        endpoint: u8,
+        data: &[u8],
+        _timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        self.writes.push((endpoint, data.to_vec()));
+        Ok(data.len())
+    }
+
+    fn write_control(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &[u8],
+        _timeout: Duration,
+    ) -> Result<usize, FlirError> {
+        self.controls.push(ControlTransfer {
+            request_type,
+            request,
+            value,
+            index,
+            data: data.to_vec(),
+        });
+        Ok(data.len())
+    }
+
+    fn claim_interface(&mut self, interface: u8) -> Result<(), FlirError> {
+        if !self.claimed.contains(&interface) {
+            self.claimed.push(interface);
+        }
+        Ok(())
+    }
+
+    fn release_interface(&mut self, interface: u8) -> Result<(), FlirError> {
+        self.claimed.retain(|&claimed| claimed != interface);
+        Ok(())
+    }
+
+    fn attach_kernel_driver(&mut self, _interface: u8) -> Result<(), FlirError> {
+        Ok(())
+    }
+
+    fn device_ids(&self) -> Result<(u16, u16), FlirError> {
+        Ok((FLIR_VENDOR_ID, FLIR_ONE_PRODUCT_IDS[0]))
+    }
+
+    fn string_descriptors(&self) -> Result<StringDescriptors, FlirError> {
+        Ok(self.strings.clone())
+    }
+}
@@ -0,0 +1,21 @@
+//! Adjustments for the decoded visual image (see [`Frame::visual_image`](crate::Frame::visual_image)).
+//! The visual sensor is small and soft; these take an image and return a new one so they
+//! can be chained before blending it with the thermal image.
+
+use image::{imageops, RgbImage};
+
+/// Adds `delta` to every channel, saturating at black and white.
+pub fn adjust_brightness(image: &RgbImage, delta: i32) -> RgbImage {
+    imageops::brighten(image, delta)
+}
+
+/// Stretches (`percent > 0`) or flattens (`percent < 0`) the contrast around mid-gray.
+pub fn adjust_contrast(image: &RgbImage, percent: f32) -> RgbImage {
+    imageops::contrast(image, percent)
+}
+
+/// Sharpens by adding back the difference from a Gaussian blur of radius `sigma`, where
+/// that difference exceeds `threshold`.
+pub fn unsharp_mask(image: &RgbImage, sigma: f32, threshold: i32) -> RgbImage {
+    imageops::unsharpen(image, sigma, threshold)
+}
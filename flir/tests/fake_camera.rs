@@ -0,0 +1,366 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use flir::{
+    capture::QueuePolicy,
+    config::ShutterAction,
+    endpoints,
+    fileio::{Progress, CAMERA_FILES},
+    test_support::{FakeCamera, FAKE_BATTERY_PERCENTAGE, HOTSPOT_RAW},
+    transport::EndpointInfo,
+    Calibration, ClaimedInterfaces, FlirError, FlirOneBuilder, Frame, MockTransport, RetryPolicy,
+    ThermalImage,
+};
+use serde_json::json;
+
+fn hottest(image: &ThermalImage) -> (u32, u32) {
+    let (i, _) = image
+        .pixels()
+        .iter()
+        .enumerate()
+        .max_by_key(|&(_, &raw)| raw)
+        .unwrap();
+    (i as u32 % image.width(), i as u32 / image.width())
+}
+
+#[test]
+fn streams_frames_with_moving_hotspot() {
+    let mut flir = FakeCamera::new().into_device();
+    flir.connect().unwrap();
+    flir.start_stream().unwrap();
+    assert!(
+        flir.wait_ready(std::time::Duration::from_secs(1))
+            .unwrap()
+            .ready
+    );
+
+    let mut previous = None;
+    for index in 0..4 {
+        let frame = flir.read_frame().unwrap();
+        let image = ThermalImage::from_frame(&frame).unwrap();
+        let (x, y) = FakeCamera::hotspot(index);
+        assert_eq!(image.get(x, y), Some(HOTSPOT_RAW));
+        let spot = hottest(&image);
+        assert_ne!(Some(spot), previous);
+        previous = Some(spot);
+    }
+    assert_eq!(flir.handle().frames_sent(), 4);
+}
+
+#[test]
+fn frame_reads_never_exceed_the_frame() {
+    let mut flir = FakeCamera::new().into_device();
+    flir.connect().unwrap();
+    flir.start_stream().unwrap();
+    for _ in 0..3 {
+        flir.read_frame().unwrap();
+    }
+
+    let reads = flir.handle().frame_reads();
+    assert!(reads.len() >= 6);
+    assert!(reads.iter().any(|read| read.requested == 512));
+    for read in reads {
+        assert_eq!(read.requested % 512, 0);
+        assert!(
+            read.requested <= read.remaining.div_ceil(512) * 512,
+            "{read:?} reads past the end of the frame"
+        );
+    }
+}
+
+#[test]
+fn stop_stream_halts_frames() {
+    let mut flir = FakeCamera::new().into_device();
+    flir.connect().unwrap();
+    flir.start_stream().unwrap();
+    flir.read_frame().unwrap();
+
+    flir.stop_stream().unwrap();
+    assert!(!flir.handle().is_streaming());
+    assert!(matches!(flir.read_frame(), Err(FlirError::Timeout { .. })));
+
+    let controls = flir.handle().controls();
+    let last = controls.last().unwrap();
+    assert_eq!((last.value, last.index), (0, 2));
+}
+
+#[test]
+fn start_stream_requires_connect() {
+    let mut flir = FakeCamera::new().into_device();
+    assert!(matches!(flir.start_stream(), Err(FlirError::NotConnected)));
+}
+
+#[test]
+fn connect_and_wait_returns_first_frame() {
+    let mut flir = FakeCamera::new().into_device();
+    let frame = flir.connect_and_wait().unwrap();
+    let image = ThermalImage::from_frame(&frame).unwrap();
+    let (x, y) = FakeCamera::hotspot(0);
+    assert_eq!(image.get(x, y), Some(HOTSPOT_RAW));
+}
+
+#[test]
+fn options_round_trip() {
+    let mut flir = FakeCamera::new().into_device();
+    flir.set_range(flir::config::RangePreset::Low).unwrap();
+    assert_eq!(flir.get_option("gainMode").unwrap(), json!("low"));
+    assert_eq!(flir.handle().option("gainMode"), Some(&json!("low")));
+    assert_eq!(flir.list_options().unwrap()[0].key, "gainMode");
+}
+
+#[test]
+fn config_channel_sends_typed_requests() {
+    let mut flir = FakeCamera::new().into_device();
+    let mut config = flir.config_channel().unwrap();
+    config.ping().unwrap();
+    let battery = config.get_battery().unwrap();
+    assert_eq!(battery.percentage, FAKE_BATTERY_PERCENTAGE);
+    assert!(!battery.charging);
+    config.set_palette("iron").unwrap();
+    config.shutter_control(ShutterAction::Calibrate).unwrap();
+    assert_eq!(flir.handle().option("palette"), Some(&json!("iron")));
+}
+
+#[test]
+fn battery_status_comes_from_periodic_messages() {
+    let mut flir = FakeCamera::new().into_device();
+    flir.connect().unwrap();
+    let battery = flir.battery_status().unwrap();
+    assert_eq!(battery.percentage, FAKE_BATTERY_PERCENTAGE);
+    assert!(!battery.charging);
+}
+
+#[test]
+fn watchdog_restarts_a_silent_stream_with_a_nuc_and_keeps_config_messages() {
+    let camera = FakeCamera::new();
+    let log = camera.log();
+    let mut flir = camera.into_device();
+    flir.connect().unwrap();
+
+    // Never started, so the camera stays silent until the watchdog starts it.
+    let mut frames = flir
+        .frames()
+        .with_watchdog(Duration::from_millis(50))
+        .with_watchdog_nuc();
+    assert!(frames.next().unwrap().is_ok());
+    assert_eq!(frames.session_stats().restarts(), 1);
+    assert!(frames.last_restart().is_some());
+    drop(frames);
+    assert!(log.requests().iter().any(|kind| kind == "shutterControl"));
+
+    // The battery message sent on connect survived the restart's flush.
+    assert_eq!(
+        flir.battery_status().unwrap().percentage,
+        FAKE_BATTERY_PERCENTAGE
+    );
+    assert!(!log.requests().iter().any(|kind| kind == "getBattery"));
+}
+
+#[test]
+fn device_info_combines_descriptors_and_config() {
+    let mut flir = FakeCamera::new().into_device();
+    let info = flir.device_info().unwrap();
+    assert!(info.strings.serial_number.is_some());
+    assert_eq!(info.firmware_version.as_deref(), Some("3.0.1"));
+    assert_eq!(info.hardware_revision.as_deref(), Some("B"));
+    assert_eq!(info.software_version, None);
+}
+
+/// A zip archive holding `files` uncompressed.
+fn stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = Vec::new();
+    let mut directory = Vec::new();
+    for (name, contents) in files {
+        let size = (contents.len() as u32).to_le_bytes();
+        let offset = (zip.len() as u32).to_le_bytes();
+        let mut local = vec![0u8; 30];
+        local[..4].copy_from_slice(&0x0403_4b50u32.to_le_bytes());
+        local[18..22].copy_from_slice(&size);
+        local[22..26].copy_from_slice(&size);
+        local[26..28].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend(local);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(contents);
+
+        let mut entry = vec![0u8; 46];
+        entry[..4].copy_from_slice(&0x0201_4b50u32.to_le_bytes());
+        entry[20..24].copy_from_slice(&size);
+        entry[24..28].copy_from_slice(&size);
+        entry[28..30].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        entry[42..46].copy_from_slice(&offset);
+        directory.extend(entry);
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let mut end = vec![0u8; 22];
+    end[..4].copy_from_slice(&0x0605_4b50u32.to_le_bytes());
+    end[10..12].copy_from_slice(&(files.len() as u16).to_le_bytes());
+    end[16..20].copy_from_slice(&(zip.len() as u32).to_le_bytes());
+    zip.extend(directory);
+    zip.extend(end);
+    zip
+}
+
+#[test]
+fn file_system_uploads_and_downloads_with_progress() {
+    let mut flir = FakeCamera::new().into_device();
+    flir.connect().unwrap();
+    let contents: Vec<u8> = (0..40_000u32).map(|i| i as u8).collect();
+
+    let mut uploaded = Vec::new();
+    let mut files = flir.file_system().unwrap();
+    files
+        .write_file_with_progress("Data/upload.bin", &contents, |p| uploaded.push(p))
+        .unwrap();
+    assert_eq!(uploaded.last().unwrap().transferred, contents.len());
+    assert_eq!(files.list_dir("Data").unwrap(), ["upload.bin"]);
+
+    let mut downloaded = Vec::new();
+    let read = files
+        .read_file_with_progress("Data/upload.bin", |p| downloaded.push(p))
+        .unwrap();
+    assert_eq!(read, contents);
+    assert!(downloaded.len() > 1);
+    assert_eq!(
+        downloaded.last().unwrap(),
+        &Progress {
+            transferred: contents.len(),
+            total: Some(contents.len()),
+        }
+    );
+    assert!(matches!(
+        files.read_file("Data/missing.bin"),
+        Err(FlirError::Protocol(_))
+    ));
+    assert_eq!(
+        flir.handle().file("Data/upload.bin"),
+        Some(contents.as_slice())
+    );
+}
+
+#[test]
+fn camera_files_are_unpacked() {
+    let zip = stored_zip(&[("conf/cal.rsc", b"planck"), ("conf/image.rsc", b"geometry")]);
+    let mut flir = FakeCamera::new().with_file(CAMERA_FILES, zip).into_device();
+    flir.connect().unwrap();
+    let files = flir.file_system().unwrap().camera_files(|_| {}).unwrap();
+    assert_eq!(
+        files,
+        [
+            ("conf/cal.rsc".to_owned(), b"planck".to_vec()),
+            ("conf/image.rsc".to_owned(), b"geometry".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn lifecycle_stops_protocols_and_releases_interfaces_on_drop() {
+    let camera = FakeCamera::new();
+    let log = camera.log();
+    let mut flir = camera.into_device();
+    assert_eq!(log.claimed(), [0, 1, 2]);
+
+    flir.connect().unwrap();
+    flir.start_stream().unwrap();
+    assert!(flir.is_streaming());
+    for index in 0..3 {
+        let image = ThermalImage::from_frame(&flir.read_frame().unwrap()).unwrap();
+        let (x, y) = FakeCamera::hotspot(index);
+        assert_eq!(image.get(x, y), Some(HOTSPOT_RAW));
+    }
+    let started = log.controls().len();
+    drop(flir);
+
+    // FRAME, FILEIO, then CONFIG are stopped, and nothing stays claimed.
+    let stops: Vec<_> = log.controls()[started..]
+        .iter()
+        .map(|control| (control.value, control.index))
+        .collect();
+    assert_eq!(stops, [(0, 2), (0, 1), (0, 0)]);
+    assert!(log.claimed().is_empty());
+    assert_eq!(log.released(), [0, 1, 2]);
+    assert!(log.reattached().is_empty());
+}
+
+#[test]
+fn drop_reattaches_detached_kernel_drivers_after_releasing() {
+    let camera = FakeCamera::new();
+    let log = camera.log();
+    let claimed = ClaimedInterfaces {
+        interfaces: vec![0, 1, 2],
+        detached: vec![2],
+        ..ClaimedInterfaces::default()
+    };
+    let flir = FlirOneBuilder::new(camera)
+        .claimed(&claimed)
+        .config_read(EndpointInfo::new(endpoints::CONFIG_READ, 512))
+        .config_write(EndpointInfo::new(endpoints::CONFIG_WRITE, 512))
+        .build()
+        .unwrap();
+    drop(flir);
+
+    assert_eq!(log.released(), [0, 1, 2]);
+    assert_eq!(log.reattached(), [2]);
+}
+
+#[test]
+fn stopping_a_capture_cancels_a_quiet_read() {
+    let flir = FakeCamera::new().into_device();
+    let capture = flir.spawn_capture(4, QueuePolicy::DropOldest);
+    thread::sleep(Duration::from_millis(50));
+    let stopped = Instant::now();
+    assert!(capture.stop().is_some());
+    assert!(stopped.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn config_reads_give_up_on_zero_length_packets() {
+    let mut transport = MockTransport::new();
+    for _ in 0..1000 {
+        transport.push_read(endpoints::CONFIG_READ, Vec::new());
+    }
+    let mut flir = FlirOneBuilder::new(transport)
+        .config_read(EndpointInfo::new(endpoints::CONFIG_READ, 512))
+        .config_write(EndpointInfo::new(endpoints::CONFIG_WRITE, 512))
+        .build()
+        .unwrap();
+    assert!(matches!(
+        flir.read_status(),
+        Err(FlirError::Timeout { partial_bytes: 0 })
+    ));
+}
+
+#[test]
+fn flushing_on_connect_does_not_retry_timeouts() {
+    let mut flir = FlirOneBuilder::new(MockTransport::new())
+        .config_read(EndpointInfo::new(endpoints::CONFIG_READ, 512))
+        .config_write(EndpointInfo::new(endpoints::CONFIG_WRITE, 512))
+        .frame_read(EndpointInfo::new(endpoints::FRAME_READ, 512))
+        .retry_policy(RetryPolicy::new(3, Duration::from_millis(500)))
+        .build()
+        .unwrap();
+    let started = Instant::now();
+    flir.connect().unwrap();
+    assert!(started.elapsed() < Duration::from_millis(500));
+}
+
+#[test]
+fn stream_statistics_do_not_drop_frames_they_cannot_decode() {
+    let mut frame = Frame::parse(&FakeCamera::frame_bytes(FakeCamera::hotspot(0))).unwrap();
+    frame.thermal_raw.truncate(100);
+    frame.header.thermal_size = 100;
+    frame.header.frame_size = 100 + frame.header.jpeg_size + frame.header.status_size;
+    let mut transport = MockTransport::new();
+    transport.push_read(endpoints::FRAME_READ, frame.to_bytes());
+    let mut flir = FlirOneBuilder::new(transport)
+        .frame_read(EndpointInfo::new(endpoints::FRAME_READ, 512))
+        .build()
+        .unwrap();
+
+    let mut frames = flir
+        .frames()
+        .with_calibration(Calibration::default_flir_one());
+    assert_eq!(frames.next().unwrap().unwrap().thermal_raw.len(), 100);
+    assert!(frames.session_stats().max().is_none());
+}
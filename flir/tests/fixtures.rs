@@ -0,0 +1,195 @@
+use std::time::SystemTime;
+
+use flir::{
+    frame::{FrameParser, HEADER_LEN, MAX_FRAME_LEN},
+    radiometry::{AtmosphericConstants, ObjectParameters},
+    test_support::load_fixture,
+    Calibration, ColorMode, FlirError, Frame, Palette, SessionStats, ThermalImage,
+};
+
+fn calibration() -> Calibration {
+    Calibration {
+        planck_r1: 16671.0,
+        planck_r2: 0.0126,
+        planck_b: 1428.0,
+        planck_f: 1.0,
+        planck_o: -6494.0,
+        atmosphere: AtmosphericConstants {
+            alpha1: 0.006569,
+            alpha2: 0.012620,
+            beta1: -0.002276,
+            beta2: -0.006670,
+            x: 1.9,
+        },
+        object: ObjectParameters::default(),
+    }
+}
+
+#[test]
+fn fixture_sections_match_header() {
+    let frame = load_fixture("hotspot");
+    assert_eq!(frame.thermal_raw.len(), frame.header.thermal_size as usize);
+    assert_eq!(frame.visual_jpeg.len(), frame.header.jpeg_size as usize);
+    assert!(frame.status_json.starts_with('{'));
+    assert!(!frame.is_shutter_frame());
+    assert_eq!(Frame::parse(&frame.to_bytes()).unwrap(), frame);
+
+    let visual = frame.visual_image().unwrap();
+    assert_eq!(visual.dimensions(), (640, 480));
+}
+
+#[test]
+fn fixture_decodes_and_colorizes() {
+    let frame = load_fixture("hotspot");
+    let image = ThermalImage::from_frame(&frame).unwrap();
+    assert_eq!((image.width(), image.height()), (160, 120));
+    assert!(image.get(112, 40).unwrap() > image.get(10, 100).unwrap());
+
+    let png = image.to_png(Palette::Grayscale).unwrap();
+    assert!(png.starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn isotherm_paints_the_alarm_range_and_rejects_empty_ones() {
+    let frame = load_fixture("hotspot");
+    let cal = calibration();
+    let image = ThermalImage::from_frame(&frame).unwrap();
+    let hot = image.to_celsius(&cal)[40 * 160 + 112];
+    let red = [255, 0, 0];
+
+    let above = ColorMode::Isotherm {
+        below: None,
+        above: Some(hot - 0.01),
+        color: red,
+    };
+    let rendered = image.render(above, &cal).unwrap();
+    assert_eq!(rendered.get_pixel(112, 40).0, red);
+    assert_ne!(rendered.get_pixel(10, 100).0, red);
+
+    let empty = ColorMode::Isotherm {
+        below: Some(10.0),
+        above: Some(30.0),
+        color: red,
+    };
+    assert!(matches!(empty.check(), Err(FlirError::InvalidFormat(_))));
+    assert!(image.render(empty, &cal).is_err());
+
+    let png = std::env::temp_dir().join("flir-isotherm-test.png");
+    assert!(matches!(
+        frame.save_png(&png, above, None),
+        Err(FlirError::NoCalibration(_))
+    ));
+    frame.save_png(&png, above, Some(&cal)).unwrap();
+    frame.save_png(&png, Palette::Ironbow, None).unwrap();
+    std::fs::remove_file(png).unwrap();
+}
+
+#[test]
+fn fixture_exports_raw_counts_as_tiff() {
+    let frame = load_fixture("hotspot");
+    let image = ThermalImage::from_frame(&frame).unwrap();
+    let tiff = image.to_tiff16();
+
+    assert!(tiff.starts_with(b"II*\0"));
+    let pixels = &tiff[tiff.len() - 160 * 120 * 2..];
+    let first = u16::from_le_bytes([pixels[0], pixels[1]]);
+    assert_eq!(Some(first), image.get(0, 0));
+    let (x, y) = (112, 40);
+    let at = (y * 160 + x) * 2;
+    let hot = u16::from_le_bytes([pixels[at], pixels[at + 1]]);
+    assert_eq!(Some(hot), image.get(x as u32, y as u32));
+}
+
+#[test]
+fn fixture_stats_find_hot_spot() {
+    let frame = load_fixture("hotspot");
+    let image = ThermalImage::from_frame(&frame).unwrap();
+    let mut stats = SessionStats::new();
+    stats.update(&image, &calibration(), SystemTime::now());
+
+    let max = stats.max().unwrap();
+    assert_eq!((max.x, max.y), (112, 40));
+    assert!(max.celsius > stats.min().unwrap().celsius);
+}
+
+#[test]
+fn fixture_temperatures_follow_raw_counts() {
+    let frame = load_fixture("hotspot");
+    let cal = calibration();
+    let temperatures = frame.temperatures(&cal).unwrap();
+    let image = ThermalImage::from_frame(&frame).unwrap();
+
+    assert_eq!(temperatures.celsius(), image.to_celsius(&cal).as_slice());
+    let (lo, hi) = temperatures.range().unwrap();
+    assert_eq!(temperatures.get(112, 40), Some(hi));
+    assert!(lo < hi);
+    assert_eq!(temperatures.get(160, 0), None);
+}
+
+#[test]
+fn diff_celsius_is_the_difference_of_temperatures() {
+    let cal = calibration();
+    let image = ThermalImage::from_frame(&load_fixture("hotspot")).unwrap();
+    let shifted = ThermalImage::new(
+        image.width(),
+        image.height(),
+        image.pixels().iter().map(|p| p + 100).collect(),
+    )
+    .unwrap();
+
+    let diff = shifted.diff_celsius(&image, &cal).unwrap();
+    let (hot, cold) = (shifted.to_celsius(&cal), image.to_celsius(&cal));
+    assert_eq!(diff.len(), cold.len());
+    for ((d, h), c) in diff.iter().zip(&hot).zip(&cold) {
+        assert_eq!(*d, h - c);
+        assert!(*d > 0.0);
+    }
+
+    let small = ThermalImage::new(2, 2, vec![0; 4]).unwrap();
+    assert!(image.diff_celsius(&small, &cal).is_err());
+}
+
+#[test]
+fn parser_reassembles_frames_split_across_transfers() {
+    let frame = load_fixture("hotspot");
+    let bytes = frame.to_bytes();
+    let mut stream = b"stale bytes".to_vec();
+    stream.extend_from_slice(&bytes);
+    stream.extend_from_slice(&bytes);
+
+    let mut parser = FrameParser::new();
+    let mut frames = Vec::new();
+    for chunk in stream.chunks(16384) {
+        parser.push(chunk);
+        while let Some(frame) = parser.next_frame().unwrap() {
+            frames.push(frame);
+        }
+    }
+    assert_eq!(frames, [frame.clone(), frame]);
+    assert_eq!(parser.buffered(), 0);
+    assert_eq!(parser.resyncs(), 0);
+}
+
+#[test]
+fn parser_checks_the_boundary_of_a_frame_read_to_its_exact_end() {
+    let bytes = load_fixture("hotspot").to_bytes();
+    let mut parser = FrameParser::new();
+    parser.push(&bytes);
+    assert!(parser.next_frame().unwrap().is_some());
+
+    parser.push(b"not a magic");
+    assert!(parser.next_frame().is_err());
+    assert_eq!(parser.resyncs(), 1);
+    parser.push(&bytes);
+    assert!(parser.next_frame().unwrap().is_some());
+}
+
+#[test]
+fn parser_rejects_oversized_frames_instead_of_buffering() {
+    let mut bytes = load_fixture("hotspot").to_bytes();
+    bytes[8..12].copy_from_slice(&(MAX_FRAME_LEN as u32).to_le_bytes());
+    let mut parser = FrameParser::new();
+    parser.push(&bytes[..HEADER_LEN]);
+    assert!(parser.next_frame().is_err());
+    assert_eq!(parser.missing(), None);
+}
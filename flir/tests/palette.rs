@@ -0,0 +1,59 @@
+use flir::{fff, Palette, PaletteLut, ThermalImage};
+
+/// A minimal radiometric JPEG whose FFF file holds only a PaletteInfo record.
+fn jpeg_with_palette(name: &str, ycrcb: &[[u8; 3]]) -> Vec<u8> {
+    let mut record = vec![0u8; 0x70];
+    record[0] = ycrcb.len() as u8;
+    record[0x50..0x50 + name.len()].copy_from_slice(name.as_bytes());
+    record.extend(ycrcb.iter().flatten());
+
+    let mut fff = vec![0u8; 64 + 32];
+    fff[..4].copy_from_slice(b"FFF\0");
+    fff[20..24].copy_from_slice(&100u32.to_be_bytes());
+    fff[24..28].copy_from_slice(&64u32.to_be_bytes());
+    fff[28..32].copy_from_slice(&1u32.to_be_bytes());
+    fff[64..66].copy_from_slice(&0x22u16.to_be_bytes());
+    let offset = fff.len() as u32;
+    fff[76..80].copy_from_slice(&offset.to_be_bytes());
+    fff[80..84].copy_from_slice(&(record.len() as u32).to_be_bytes());
+    fff.extend(record);
+
+    let mut segment = b"FLIR\0\x01\x00\x00".to_vec();
+    segment.extend(fff);
+    let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe1];
+    jpeg.extend(((segment.len() + 2) as u16).to_be_bytes());
+    jpeg.extend(segment);
+    jpeg.extend([0xff, 0xd9]);
+    jpeg
+}
+
+#[test]
+fn embedded_palette_is_converted_to_rgb() {
+    let jpeg = jpeg_with_palette("iron", &[[0, 128, 128], [76, 255, 85], [255, 128, 128]]);
+    let lut = fff::parse_palette(&jpeg).unwrap();
+    assert_eq!(lut.name(), "iron");
+    assert_eq!(lut.colors(), &[[0, 0, 0], [254, 0, 0], [255, 255, 255]]);
+    assert_eq!(lut.color(0.0), [0, 0, 0]);
+    assert_eq!(lut.color(0.5), [254, 0, 0]);
+    assert_eq!(lut.color(2.0), [255, 255, 255]);
+}
+
+#[test]
+fn colorize_lut_spans_the_table() {
+    let lut = PaletteLut::new("ramp", vec![[0, 0, 255], [255, 0, 0]]).unwrap();
+    let image = ThermalImage::new(2, 1, vec![1000, 2000]).unwrap();
+    let rgb = image.colorize_lut(&lut);
+    assert_eq!(rgb.get_pixel(0, 0).0, [0, 0, 255]);
+    assert_eq!(rgb.get_pixel(1, 0).0, [255, 0, 0]);
+    assert!(PaletteLut::new("empty", Vec::new()).is_err());
+}
+
+#[test]
+fn builtin_gradients_run_cold_to_hot() {
+    assert_eq!(Palette::Ironbow.color(0.0), [0, 0, 0]);
+    assert_eq!(Palette::Ironbow.color(1.0), [255, 255, 255]);
+    assert_eq!(Palette::Rainbow.color(0.0), [0, 0, 255]);
+    assert_eq!(Palette::Rainbow.color(0.5), [0, 255, 0]);
+    assert_eq!(Palette::Rainbow.color(1.0), [255, 0, 0]);
+    assert_eq!(Palette::Arctic.color(2.0), Palette::Arctic.color(1.0));
+}